@@ -0,0 +1,74 @@
+#![no_main]
+
+use algs4::dary_heap::DaryHeap;
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use std::collections::BinaryHeap;
+
+#[derive(Arbitrary, Debug)]
+enum Op {
+    Push(u8),
+    Pop,
+    PeekMut(i8),
+    Clear,
+    Extend(Vec<u8>),
+    Retain(u8),
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    arity: u8,
+    ops: Vec<Op>,
+}
+
+// Replays the operations on a DaryHeap and a BinaryHeap side by side
+fn run<const D: usize>(ops: &[Op]) {
+    let mut heap = DaryHeap::<i32, D>::new();
+    let mut std_heap = BinaryHeap::new();
+    for op in ops {
+        match *op {
+            Op::Push(num) => {
+                heap.push(num as i32);
+                std_heap.push(num as i32);
+            }
+            Op::Pop => assert_eq!(heap.pop(), std_heap.pop()),
+            Op::PeekMut(delta) => {
+                if let Some(mut top) = heap.peek_mut() {
+                    *top = top.wrapping_add(delta as i32);
+                    let mut std_top = std_heap.peek_mut().unwrap();
+                    *std_top = std_top.wrapping_add(delta as i32);
+                }
+            }
+            Op::Clear => {
+                heap.clear();
+                std_heap.clear();
+            }
+            Op::Extend(ref nums) => {
+                heap.extend(nums.iter().map(|&num| num as i32));
+                std_heap.extend(nums.iter().map(|&num| num as i32));
+            }
+            Op::Retain(modulus) => {
+                let keep = |&num: &i32| num % (modulus as i32 + 1) != 0;
+                heap.retain(keep);
+                std_heap.retain(keep);
+            }
+        }
+        heap.check_invariants();
+        assert_eq!(heap.len(), std_heap.len());
+        assert_eq!(heap.peek(), std_heap.peek());
+    }
+    assert_eq!(heap.into_sorted_vec(), std_heap.into_sorted_vec());
+}
+
+fuzz_target!(|input: Input| {
+    match input.arity % 8 {
+        0 => run::<2>(&input.ops),
+        1 => run::<3>(&input.ops),
+        2 => run::<4>(&input.ops),
+        3 => run::<5>(&input.ops),
+        4 => run::<7>(&input.ops),
+        5 => run::<8>(&input.ops),
+        6 => run::<16>(&input.ops),
+        _ => run::<17>(&input.ops),
+    }
+});
@@ -0,0 +1,340 @@
+use crate::compare::{Compare, MaxComparator};
+use crate::dary_heap::{sift_down_to_bottom, sift_up};
+use likely_stable::unlikely;
+use std::{
+    alloc::{self, Layout},
+    fmt::{self, Debug},
+    marker::PhantomData,
+    mem,
+    ptr::{self, NonNull},
+    slice,
+};
+
+const CACHE_LINE: usize = 64;
+
+// D-ary heap whose sibling groups start on cache line boundaries. The buffer
+// is 64-byte aligned and the root is stored at offset D - 1, so the children
+// i * D + 1 ..= i * D + D of node i land at offsets D * (i + 1) onwards. The
+// group size D * size_of::<T>() must divide 64, so every group of siblings sits
+// in one cache line, or be a multiple of it, so every group starts a line and
+// fills whole lines. Other combinations of T and D fail to compile. With
+// PREFETCH, the grandchildren are prefetched while sifting down.
+pub struct AlignedDaryHeap<T, const D: usize, C = MaxComparator, const PREFETCH: bool = false> {
+    // Start of the allocation, the first D - 1 slots are padding
+    buf: NonNull<T>,
+    // In elements, padding included, 0 while nothing is allocated
+    cap: usize,
+    len: usize,
+    cmp: C,
+    _marker: PhantomData<T>,
+}
+
+unsafe impl<T: Send, const D: usize, C: Send, const PREFETCH: bool> Send
+    for AlignedDaryHeap<T, D, C, PREFETCH>
+{
+}
+
+unsafe impl<T: Sync, const D: usize, C: Sync, const PREFETCH: bool> Sync
+    for AlignedDaryHeap<T, D, C, PREFETCH>
+{
+}
+
+impl<T, const D: usize, C: Compare<T> + Default, const PREFETCH: bool>
+    AlignedDaryHeap<T, D, C, PREFETCH>
+{
+    #[inline]
+    pub fn new() -> Self {
+        Self::with_comparator(C::default())
+    }
+
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_comparator(capacity, C::default())
+    }
+}
+
+impl<T, const D: usize, C: Compare<T>, const PREFETCH: bool> AlignedDaryHeap<T, D, C, PREFETCH> {
+    const ASSERTIONS: () = {
+        assert!(D >= 2, "D must be at least 2");
+        assert!(
+            mem::size_of::<T>() > 0,
+            "zero-sized types are not supported"
+        );
+        assert!(
+            CACHE_LINE.is_multiple_of(D * mem::size_of::<T>())
+                || (D * mem::size_of::<T>()).is_multiple_of(CACHE_LINE),
+            "D * size_of::<T>() must divide or be a multiple of the cache line size"
+        );
+    };
+
+    #[inline]
+    pub fn with_comparator(cmp: C) -> Self {
+        Self::with_capacity_and_comparator(0, cmp)
+    }
+
+    pub fn with_capacity_and_comparator(capacity: usize, cmp: C) -> Self {
+        let () = Self::ASSERTIONS;
+        let mut heap = AlignedDaryHeap {
+            buf: NonNull::dangling(),
+            cap: 0,
+            len: 0,
+            cmp,
+            _marker: PhantomData,
+        };
+        if capacity > 0 {
+            heap.grow_to(D - 1 + capacity);
+        }
+        heap
+    }
+
+    #[inline]
+    pub fn comparator(&self) -> &C {
+        &self.cmp
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Number of elements the heap holds without reallocating
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.cap.saturating_sub(D - 1)
+    }
+
+    #[inline]
+    pub fn peek(&self) -> Option<&T> {
+        self.as_slice().first()
+    }
+
+    // Arbitrary order
+    #[inline]
+    pub fn iter(&self) -> slice::Iter<'_, T> {
+        self.as_slice().iter()
+    }
+
+    #[inline]
+    pub fn push(&mut self, item: T) {
+        if unlikely(D - 1 + self.len >= self.cap) {
+            self.grow_to((2 * self.cap).max(D - 1 + 4 * D));
+        }
+        // SAFETY: the buffer has room for one more element
+        unsafe {
+            let root = self.root();
+            ptr::write(root.add(self.len), item);
+            self.len += 1;
+            sift_up::<_, _, D>(
+                slice::from_raw_parts_mut(root, self.len),
+                &self.cmp,
+                0,
+                self.len - 1,
+            );
+        }
+    }
+
+    #[inline]
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        // SAFETY: the heap is not empty, so the buffer is allocated. The
+        // moved-out root is refilled before anything can panic.
+        unsafe {
+            let root = self.root();
+            self.len -= 1;
+            let item = ptr::read(root.add(self.len));
+            if unlikely(self.len == 0) {
+                return Some(item);
+            }
+            let top = ptr::read(root);
+            sift_down_to_bottom::<_, _, D, PREFETCH>(
+                slice::from_raw_parts_mut(root, self.len),
+                &self.cmp,
+                0,
+                item,
+            );
+            Some(top)
+        }
+    }
+
+    pub fn clear(&mut self) {
+        if self.len == 0 {
+            return;
+        }
+        let len = mem::replace(&mut self.len, 0);
+        // SAFETY: the first len elements are initialized, and no longer
+        // reachable once len is 0
+        unsafe {
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(self.root(), len));
+        }
+    }
+
+    #[inline]
+    fn as_slice(&self) -> &[T] {
+        if self.cap == 0 {
+            return &[];
+        }
+        // SAFETY: the buffer is allocated and holds len elements from the root
+        unsafe { slice::from_raw_parts(self.root(), self.len) }
+    }
+
+    // SAFETY: the buffer is allocated
+    #[inline]
+    unsafe fn root(&self) -> *mut T {
+        unsafe { self.buf.as_ptr().add(D - 1) }
+    }
+
+    fn grow_to(&mut self, cap: usize) {
+        let layout = buffer_layout::<T>(cap);
+        // SAFETY: T is not zero-sized and cap > 0, so the layout is not empty
+        let ptr = unsafe {
+            if self.cap == 0 {
+                alloc::alloc(layout)
+            } else {
+                alloc::realloc(
+                    self.buf.as_ptr() as *mut u8,
+                    buffer_layout::<T>(self.cap),
+                    layout.size(),
+                )
+            }
+        };
+        self.buf = NonNull::new(ptr as *mut T).unwrap_or_else(|| alloc::handle_alloc_error(layout));
+        self.cap = cap;
+    }
+}
+
+fn buffer_layout<T>(cap: usize) -> Layout {
+    Layout::array::<T>(cap)
+        .and_then(|layout| layout.align_to(CACHE_LINE))
+        .expect("capacity overflow")
+}
+
+impl<T, const D: usize, C, const PREFETCH: bool> Drop for AlignedDaryHeap<T, D, C, PREFETCH> {
+    fn drop(&mut self) {
+        if self.cap == 0 {
+            return;
+        }
+        // SAFETY: the buffer is allocated with this layout and holds len
+        // elements from offset D - 1
+        unsafe {
+            let buf = self.buf.as_ptr();
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(buf.add(D - 1), self.len));
+            alloc::dealloc(buf as *mut u8, buffer_layout::<T>(self.cap));
+        }
+    }
+}
+
+impl<T, const D: usize, C: Compare<T> + Default, const PREFETCH: bool> Default
+    for AlignedDaryHeap<T, D, C, PREFETCH>
+{
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Debug, const D: usize, C: Compare<T>, const PREFETCH: bool> Debug
+    for AlignedDaryHeap<T, D, C, PREFETCH>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T, const D: usize, C: Compare<T>, const PREFETCH: bool> Extend<T>
+    for AlignedDaryHeap<T, D, C, PREFETCH>
+{
+    #[inline]
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|item| self.push(item));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compare::MinComparator;
+    use rand::Rng;
+    use std::collections::BinaryHeap;
+    use std::rc::Rc;
+
+    fn check_random_operations<const D: usize, const PREFETCH: bool>() {
+        let mut rng = rand::rng();
+        let mut heap = AlignedDaryHeap::<u64, D, MaxComparator, PREFETCH>::new();
+        let mut reference = BinaryHeap::new();
+        (0..if cfg!(miri) { 500 } else { 50000 }).for_each(|_| {
+            if rng.random_bool(0.6) {
+                let num = rng.random_range(0..1000);
+                heap.push(num);
+                reference.push(num);
+            } else {
+                assert_eq!(heap.pop(), reference.pop());
+            }
+            assert_eq!(heap.peek(), reference.peek());
+        });
+        assert_eq!(heap.len(), reference.len());
+        while let Some(num) = reference.pop() {
+            assert_eq!(heap.pop(), Some(num));
+        }
+        assert_eq!(heap.pop(), None);
+    }
+
+    #[test]
+    fn test_random_operations() {
+        check_random_operations::<2, false>();
+        check_random_operations::<4, true>();
+        check_random_operations::<8, false>();
+        check_random_operations::<8, true>();
+        check_random_operations::<16, true>();
+    }
+
+    #[test]
+    fn test_layout() {
+        let mut heap = AlignedDaryHeap::<u64, 8, MinComparator>::with_capacity(100);
+        assert!(heap.capacity() >= 100);
+        heap.extend((0..1000).rev());
+        let root = heap.peek().unwrap() as *const u64 as usize;
+        assert_eq!(*heap.peek().unwrap(), 0);
+        for i in 0..1000 / 8 {
+            assert_eq!((root + (i * 8 + 1) * 8) % CACHE_LINE, 0);
+        }
+        assert_eq!(format!("{:?}", AlignedDaryHeap::<u64, 4>::new()), "[]");
+
+        // A sibling group spanning three cache lines
+        let mut heap = AlignedDaryHeap::<[u64; 8], 3, MinComparator>::new();
+        heap.extend((0..100).rev().map(|i| [i; 8]));
+        let root = heap.peek().unwrap() as *const [u64; 8] as usize;
+        for i in 0..100 / 3 {
+            assert_eq!((root + (i * 3 + 1) * 64) % CACHE_LINE, 0);
+        }
+
+        // Four sibling groups sharing a cache line
+        let mut heap = AlignedDaryHeap::<u32, 4, MinComparator>::new();
+        heap.extend((0..1000).rev());
+        let root = heap.peek().unwrap() as *const u32 as usize;
+        for i in 0..1000 / 4 {
+            let group = root + (i * 4 + 1) * 4;
+            assert_eq!(group % 16, 0);
+            assert_eq!(group / CACHE_LINE, (group + 15) / CACHE_LINE);
+        }
+    }
+
+    #[test]
+    fn test_drop() {
+        let item = Rc::new(());
+        let mut heap = AlignedDaryHeap::<(i32, Rc<()>), 4>::new();
+        (0..100).for_each(|i| heap.push((i, item.clone())));
+        heap.pop();
+        heap.clear();
+        assert_eq!(Rc::strong_count(&item), 1);
+        (0..10).for_each(|i| heap.push((i, item.clone())));
+        drop(heap);
+        assert_eq!(Rc::strong_count(&item), 1);
+    }
+}
@@ -0,0 +1,448 @@
+use crate::graph::{DijkstraQueue, Graph};
+use std::io::{self, Read, Write};
+
+const NO_PRED: u32 = u32::MAX;
+const MAGIC: &[u8; 4] = b"APSP";
+
+// Row `u` holds the distances from `u` and the predecessors on its shortest path tree
+pub struct DistanceMatrix {
+    n: usize,
+    dist: Vec<f64>,
+    pred: Vec<u32>,
+}
+
+impl DistanceMatrix {
+    #[inline]
+    fn new(n: usize) -> Self {
+        assert!(n < NO_PRED as usize, "too many nodes for a distance matrix");
+        let mut dist = vec![f64::INFINITY; n * n];
+        (0..n).for_each(|u| dist[u * n + u] = 0.0);
+        DistanceMatrix {
+            n,
+            dist,
+            pred: vec![NO_PRED; n * n],
+        }
+    }
+
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.n
+    }
+
+    #[inline]
+    pub fn dist(&self, u: usize, v: usize) -> f64 {
+        self.dist[u * self.n + v]
+    }
+
+    #[inline]
+    pub fn has_path(&self, u: usize, v: usize) -> bool {
+        self.dist(u, v) < f64::INFINITY
+    }
+
+    // None as well if the predecessors do not lead back to u within n steps,
+    // as with a cycle left in them or a corrupt file
+    pub fn path(&self, u: usize, v: usize) -> Option<Vec<usize>> {
+        if !self.has_path(u, v) {
+            return None;
+        }
+        let row = &self.pred[u * self.n..(u + 1) * self.n];
+        let mut path = vec![v];
+        let mut current = v;
+        while current != u {
+            if path.len() > self.n || row[current] == NO_PRED {
+                return None;
+            }
+            current = row[current] as usize;
+            path.push(current);
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    // A cycle in the predecessors of row u, which has a negative diagonal,
+    // as `v, ..., v`. Every node reached from u has a predecessor, u included.
+    fn pred_cycle(&self, u: usize) -> Vec<usize> {
+        let row = &self.pred[u * self.n..(u + 1) * self.n];
+        // Walking back n steps is guaranteed to land on the cycle
+        let mut v = u;
+        (0..self.n).for_each(|_| v = row[v] as usize);
+        let mut cycle = vec![v];
+        let mut current = row[v] as usize;
+        while current != v {
+            cycle.push(current);
+            current = row[current] as usize;
+        }
+        cycle.push(v);
+        cycle.reverse();
+        cycle
+    }
+
+    // Little-endian: magic, n as u64, n * n distances as f64, n * n predecessors as u32
+    pub fn write_to<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        out.write_all(MAGIC)?;
+        out.write_all(&(self.n as u64).to_le_bytes())?;
+        for &d in &self.dist {
+            out.write_all(&d.to_le_bytes())?;
+        }
+        for &p in &self.pred {
+            out.write_all(&p.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    // Rejects headers and contents that do not describe a matrix with
+    // `io::ErrorKind::InvalidData`, before allocating anything for it
+    pub fn read_from<R: Read>(input: &mut R) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not a distance matrix file"));
+        }
+        let mut n = [0; 8];
+        input.read_exact(&mut n)?;
+        let n = usize::try_from(u64::from_le_bytes(n))
+            .ok()
+            .filter(|&n| n < NO_PRED as usize)
+            .ok_or_else(|| invalid_data("too many nodes for a distance matrix"))?;
+        let (cells, len) = n
+            .checked_mul(n)
+            .and_then(|cells| Some((cells, cells.checked_mul(12)?)))
+            .ok_or_else(|| invalid_data("too many nodes for a distance matrix"))?;
+
+        // Grows with the bytes actually present, so a lying header costs nothing
+        let mut payload = Vec::new();
+        input.take(len as u64).read_to_end(&mut payload)?;
+        if payload.len() < len {
+            return Err(invalid_data("truncated distance matrix file"));
+        }
+        let (dist, pred) = payload.split_at(cells * 8);
+        let dist = dist
+            .chunks_exact(8)
+            .map(|bytes| f64::from_le_bytes(bytes.try_into().unwrap()))
+            .collect();
+        let pred: Vec<u32> = pred
+            .chunks_exact(4)
+            .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+            .collect();
+        if pred.iter().any(|&p| p != NO_PRED && p as usize >= n) {
+            return Err(invalid_data("predecessor out of range"));
+        }
+        Ok(DistanceMatrix { n, dist, pred })
+    }
+}
+
+#[inline]
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// O(n^3), suited to dense graphs. A negative cycle is returned as `v, ..., v`.
+pub fn floyd_warshall<
+    const CALCPATH: bool,
+    const HEURISTIC: bool,
+    const EARLYSTOP: bool,
+    Q: DijkstraQueue,
+>(
+    graph: &Graph<CALCPATH, HEURISTIC, EARLYSTOP, Q>,
+) -> Result<DistanceMatrix, Vec<usize>> {
+    let n = graph.num_nodes();
+    let mut matrix = DistanceMatrix::new(n);
+    for (u, v, weight) in graph.edges() {
+        if weight < matrix.dist[u * n + v] {
+            matrix.dist[u * n + v] = weight;
+            matrix.pred[u * n + v] = u as u32;
+        }
+    }
+
+    let mut row_k = vec![0.0; n];
+    let mut pred_k = vec![0; n];
+    for k in 0..n {
+        row_k.copy_from_slice(&matrix.dist[k * n..(k + 1) * n]);
+        pred_k.copy_from_slice(&matrix.pred[k * n..(k + 1) * n]);
+        for i in 0..n {
+            let dist_ik = matrix.dist[i * n + k];
+            if dist_ik == f64::INFINITY {
+                continue;
+            }
+            let row_i = &mut matrix.dist[i * n..(i + 1) * n];
+            let pred_i = &mut matrix.pred[i * n..(i + 1) * n];
+            for j in 0..n {
+                let next_dist = dist_ik + row_k[j];
+                if next_dist < row_i[j] {
+                    row_i[j] = next_dist;
+                    pred_i[j] = pred_k[j];
+                }
+            }
+        }
+    }
+
+    for u in 0..n {
+        if matrix.dist[u * n + u] >= 0.0 {
+            continue;
+        }
+        let cycle = matrix.pred_cycle(u);
+        // Rounding can close a zero-weight cycle slightly below 0
+        let (weight, magnitude) = cycle
+            .windows(2)
+            .map(|pair| {
+                let weight = graph
+                    .adj(pair[0])
+                    .filter(|&(to, _)| to == pair[1])
+                    .map(|(_, weight)| weight)
+                    .fold(f64::INFINITY, f64::min);
+                (weight, weight.abs())
+            })
+            .fold((0.0, 0.0), |(sum, abs), (w, a)| (sum + w, abs + a));
+        if weight < -(cycle.len() as f64) * f64::EPSILON * magnitude {
+            return Err(cycle);
+        }
+        matrix.dist[u * n + u] = 0.0;
+        matrix.pred[u * n + u] = NO_PRED;
+    }
+    Ok(matrix)
+}
+
+// Bellman-Ford potentials make every edge weight non-negative, then one
+// Dijkstra search runs from each node on the reweighted graph.
+pub fn johnson<
+    const CALCPATH: bool,
+    const HEURISTIC: bool,
+    const EARLYSTOP: bool,
+    Q: DijkstraQueue,
+>(
+    graph: &Graph<CALCPATH, HEURISTIC, EARLYSTOP, Q>,
+) -> Result<DistanceMatrix, Vec<usize>> {
+    let n = graph.num_nodes();
+    // Equivalent to a virtual source with a zero-weight edge to every node
+    let mut potential = vec![0.0; n];
+    bellman_ford(graph, &mut potential)?;
+
+    let mut reweighted = Graph::<true, false, false>::without_coords(n);
+    for (u, v, weight) in graph.edges() {
+        // Clamp rounding noise, Dijkstra needs non-negative weights
+        reweighted.add_weighted_edge(u, v, (weight + potential[u] - potential[v]).max(0.0));
+    }
+
+    let mut matrix = DistanceMatrix::new(n);
+    for u in 0..n {
+        reweighted.dijkstra(u, u);
+        let dist = &mut matrix.dist[u * n..(u + 1) * n];
+        let pred = &mut matrix.pred[u * n..(u + 1) * n];
+        for v in 0..n {
+            let d = reweighted.dist_to(v);
+            if d < f64::INFINITY && v != u {
+                dist[v] = d - potential[u] + potential[v];
+                pred[v] = reweighted.parent(v) as u32;
+            }
+        }
+    }
+    Ok(matrix)
+}
+
+// Relaxes `dist` in place, a relaxation in the n-th pass proves a negative cycle
+fn bellman_ford<
+    const CALCPATH: bool,
+    const HEURISTIC: bool,
+    const EARLYSTOP: bool,
+    Q: DijkstraQueue,
+>(
+    graph: &Graph<CALCPATH, HEURISTIC, EARLYSTOP, Q>,
+    dist: &mut [f64],
+) -> Result<(), Vec<usize>> {
+    let n = graph.num_nodes();
+    let mut parent = vec![usize::MAX; n];
+    for pass in 0..n {
+        let mut relaxed = None;
+        for (u, v, weight) in graph.edges() {
+            let next_dist = dist[u] + weight;
+            if next_dist < dist[v] {
+                dist[v] = next_dist;
+                parent[v] = u;
+                relaxed = Some(v);
+            }
+        }
+        match relaxed {
+            None => return Ok(()),
+            Some(mut v) if pass == n - 1 => {
+                // Walking back n steps is guaranteed to land on the cycle
+                (0..n).for_each(|_| v = parent[v]);
+                let mut cycle = vec![v];
+                let mut current = parent[v];
+                while current != v {
+                    cycle.push(current);
+                    current = parent[current];
+                }
+                cycle.push(v);
+                cycle.reverse();
+                return Err(cycle);
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::PairingQueue;
+    use rand::Rng;
+
+    const EPSILON: f64 = 1e-9;
+
+    // Weights shifted by random potentials: some are negative but no cycle is
+    fn random_graph(n: usize, m: usize) -> Graph<false, false, false> {
+        let mut rng = rand::rng();
+        let shift: Vec<f64> = (0..n).map(|_| rng.random_range(0.0..10.0)).collect();
+        let mut graph = Graph::without_coords(n);
+        for _ in 0..m {
+            let u = rng.random_range(0..n);
+            let v = rng.random_range(0..n);
+            let weight = rng.random_range(0.0..5.0) + shift[u] - shift[v];
+            graph.add_weighted_edge(u, v, weight);
+        }
+        graph
+    }
+
+    fn check_paths(graph: &Graph<false, false, false>, matrix: &DistanceMatrix) {
+        let n = graph.num_nodes();
+        for u in 0..n {
+            for v in 0..n {
+                let Some(path) = matrix.path(u, v) else {
+                    assert!(!matrix.has_path(u, v));
+                    continue;
+                };
+                assert_eq!(path.first(), Some(&u));
+                assert_eq!(path.last(), Some(&v));
+                let length: f64 = path
+                    .windows(2)
+                    .map(|pair| {
+                        graph
+                            .adj(pair[0])
+                            .filter(|&(to, _)| to == pair[1])
+                            .map(|(_, weight)| weight)
+                            .fold(f64::INFINITY, f64::min)
+                    })
+                    .sum();
+                assert!((length - matrix.dist(u, v)).abs() < EPSILON);
+            }
+        }
+    }
+
+    #[test]
+    fn test_johnson_matches_floyd_warshall() {
+        for _ in 0..20 {
+            let graph = random_graph(40, 150);
+            let floyd = floyd_warshall(&graph).unwrap();
+            let johnson = johnson(&graph).unwrap();
+            for u in 0..40 {
+                for v in 0..40 {
+                    assert_eq!(floyd.has_path(u, v), johnson.has_path(u, v));
+                    if floyd.has_path(u, v) {
+                        assert!((floyd.dist(u, v) - johnson.dist(u, v)).abs() < EPSILON);
+                    }
+                }
+            }
+            check_paths(&graph, &floyd);
+            check_paths(&graph, &johnson);
+        }
+    }
+
+    #[test]
+    fn test_negative_cycle() {
+        let mut graph = Graph::<false, false, false, PairingQueue>::without_coords(5);
+        graph.add_weighted_edge(0, 1, 1.0);
+        graph.add_weighted_edge(1, 2, -2.0);
+        graph.add_weighted_edge(2, 3, 1.0);
+        graph.add_weighted_edge(3, 1, -0.5);
+        graph.add_weighted_edge(3, 4, 1.0);
+
+        for cycle in [johnson(&graph).err(), floyd_warshall(&graph).err()] {
+            let cycle = cycle.unwrap();
+            assert_eq!(cycle.first(), cycle.last());
+            assert_eq!(cycle.len(), 4);
+            let weight: f64 = cycle
+                .windows(2)
+                .map(|pair| graph.adj(pair[0]).find(|&(to, _)| to == pair[1]).unwrap().1)
+                .sum();
+            assert!(weight < 0.0);
+        }
+    }
+
+    #[test]
+    fn test_zero_weight_cycle() {
+        // 0.3 - 0.1 - 0.2 rounds below 0 when summed in this order
+        let mut graph = Graph::<false, false, false>::without_coords(5);
+        graph.add_weighted_edge(4, 0, 1.0);
+        graph.add_weighted_edge(0, 1, 0.3);
+        graph.add_weighted_edge(1, 2, -0.1);
+        graph.add_weighted_edge(2, 0, -0.2);
+        graph.add_weighted_edge(2, 3, 2.0);
+
+        for matrix in [floyd_warshall(&graph).unwrap(), johnson(&graph).unwrap()] {
+            (0..5).for_each(|u| assert!(matrix.dist(u, u).abs() < EPSILON));
+            assert!((matrix.dist(4, 3) - 3.2).abs() < EPSILON);
+            assert_eq!(matrix.path(4, 3), Some(vec![4, 0, 1, 2, 3]));
+            assert_eq!(matrix.path(1, 1), Some(vec![1]));
+            for u in 0..5 {
+                for v in 0..5 {
+                    let path = matrix.path(u, v).unwrap_or_else(|| vec![u, v]);
+                    assert_eq!((path[0], path[path.len() - 1]), (u, v));
+                    assert!(path.len() <= 5);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_write_and_read() {
+        let graph = random_graph(30, 80);
+        let matrix = johnson(&graph).unwrap();
+        let mut bytes = Vec::new();
+        matrix.write_to(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 12 + 30 * 30 * 12);
+
+        let loaded = DistanceMatrix::read_from(&mut bytes.as_slice()).unwrap();
+        assert_eq!(loaded.size(), 30);
+        for u in 0..30 {
+            for v in 0..30 {
+                assert_eq!(loaded.dist(u, v).to_bits(), matrix.dist(u, v).to_bits());
+                assert_eq!(loaded.path(u, v), matrix.path(u, v));
+            }
+        }
+        assert!(DistanceMatrix::read_from(&mut &b"nope"[..]).is_err());
+
+        let invalid = |bytes: &[u8]| {
+            DistanceMatrix::read_from(&mut &bytes[..])
+                .err()
+                .unwrap()
+                .kind()
+                == io::ErrorKind::InvalidData
+        };
+        assert!(invalid(&bytes[..bytes.len() - 1]));
+        assert!(invalid(&bytes[..20]));
+        // Huge or overflowing node counts without the data to back them
+        for n in [u64::MAX, 1 << 31, 1 << 20] {
+            let mut header = MAGIC.to_vec();
+            header.extend_from_slice(&n.to_le_bytes());
+            assert!(invalid(&header));
+        }
+        let mut corrupt = bytes.clone();
+        let last = corrupt.len() - 4;
+        corrupt[last..].copy_from_slice(&30u32.to_le_bytes());
+        assert!(invalid(&corrupt));
+
+        // Predecessors running in a cycle that never reaches the source
+        let mut cyclic = MAGIC.to_vec();
+        cyclic.extend_from_slice(&2u64.to_le_bytes());
+        for d in [0.0, 1.0, f64::INFINITY, 0.0] {
+            cyclic.extend_from_slice(&f64::to_le_bytes(d));
+        }
+        for p in [NO_PRED, 1, NO_PRED, NO_PRED] {
+            cyclic.extend_from_slice(&p.to_le_bytes());
+        }
+        let loaded = DistanceMatrix::read_from(&mut cyclic.as_slice()).unwrap();
+        assert_eq!(loaded.path(0, 1), None);
+        assert_eq!(loaded.path(0, 0), Some(vec![0]));
+    }
+}
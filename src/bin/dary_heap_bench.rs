@@ -1,22 +1,483 @@
+use algs4::aligned_dary_heap::AlignedDaryHeap;
+use algs4::compare::MaxComparator;
 use algs4::dary_heap::DaryHeap;
+use algs4::fibonacci_heap::FibonacciHeap;
+use algs4::graph::DistComparator;
+use algs4::pairing_heap::PairingHeap;
 use clap::{Arg, Command};
+use dary_heap::DaryHeap as CrateDaryHeap;
 use ordered_float::NotNan;
-use rand::{distr::StandardUniform, prelude::Distribution, random};
+use rand::Rng;
 use std::collections::BinaryHeap;
-use std::time::Instant;
+use std::fs::{self, File};
+use std::hint::black_box;
+use std::io::{BufWriter, Write};
+use std::marker::PhantomData;
+use std::path::Path;
+use std::time::{Duration, Instant};
 
-fn generate_random_array<T>(size: usize) -> Vec<T>
-where
-    T: Copy,
-    StandardUniform: Distribution<T>,
+// 单调负载中每次弹出后最多压入的元素数, 近似道路网的平均出度
+const FANOUT: usize = 3;
+
+// 测试元素: 由 u32 键构造, 按键比较
+trait BenchItem: Ord + Copy {
+    const NAME: &'static str;
+    fn from_key(key: u32) -> Self;
+    fn key(&self) -> u32;
+
+    // 三叉对齐堆的兄弟节点组占 3 * size_of::<Self>() 字节, 只有是 64 的倍数时才能对齐,
+    // 由满足的类型覆盖, 其余类型不实例化. 返回是否运行
+    fn run_aligned_ternary<W: Write, const PREFETCH: bool>(_bench: &mut Bench<'_, W>) -> bool {
+        false
+    }
+}
+
+impl BenchItem for u32 {
+    const NAME: &'static str = "u32";
+
+    #[inline]
+    fn from_key(key: u32) -> Self {
+        key
+    }
+
+    #[inline]
+    fn key(&self) -> u32 {
+        *self
+    }
+}
+
+impl BenchItem for NotNan<f64> {
+    const NAME: &'static str = "f64";
+
+    #[inline]
+    fn from_key(key: u32) -> Self {
+        NotNan::new(key as f64).unwrap()
+    }
+
+    #[inline]
+    fn key(&self) -> u32 {
+        self.into_inner() as u32
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Bytes16 {
+    key: u32,
+    payload: [u32; 3],
+}
+
+impl BenchItem for Bytes16 {
+    const NAME: &'static str = "struct16";
+
+    #[inline]
+    fn from_key(key: u32) -> Self {
+        Bytes16 {
+            key,
+            payload: [key; 3],
+        }
+    }
+
+    #[inline]
+    fn key(&self) -> u32 {
+        self.key
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Bytes64 {
+    key: u32,
+    payload: [u32; 15],
+}
+
+impl BenchItem for Bytes64 {
+    const NAME: &'static str = "struct64";
+
+    #[inline]
+    fn from_key(key: u32) -> Self {
+        Bytes64 {
+            key,
+            payload: [key; 15],
+        }
+    }
+
+    #[inline]
+    fn key(&self) -> u32 {
+        self.key
+    }
+
+    // 3 * 64 字节, 每组占三个完整的缓存行
+    fn run_aligned_ternary<W: Write, const PREFETCH: bool>(bench: &mut Bench<'_, W>) -> bool {
+        bench.run::<Self, AlignedDaryHeap<Self, 3, MaxComparator, PREFETCH>>();
+        true
+    }
+}
+
+// Dijkstra 实际使用的队列: (原始 f64 距离, 节点) 配合 DistComparator 的小顶堆,
+// 键取负后即为大顶堆, 用于与 NotNan<f64> 的 dary 对比. 元素带节点下标, 占 16 字节
+struct DistHeap<T, const D: usize>(DaryHeap<(f64, usize), D, DistComparator>, PhantomData<T>);
+
+// 被测的堆实现, 均为大顶堆
+trait BenchHeap<T: Ord>: Sized {
+    const NAME: &'static str;
+    // 与 D 无关的实现为 None
+    const ARITY: Option<usize>;
+
+    fn new() -> Self;
+    fn push(&mut self, item: T);
+    fn pop(&mut self) -> Option<T>;
+
+    // 没有 O(n) 建堆的实现逐个压入
+    #[inline]
+    fn from_vec(items: Vec<T>) -> Self {
+        let mut heap = Self::new();
+        items.into_iter().for_each(|item| heap.push(item));
+        heap
+    }
+}
+
+impl<T: Ord, const D: usize> BenchHeap<T> for DaryHeap<T, D> {
+    const NAME: &'static str = "dary";
+    const ARITY: Option<usize> = Some(D);
+
+    #[inline]
+    fn new() -> Self {
+        DaryHeap::new()
+    }
+
+    #[inline]
+    fn push(&mut self, item: T) {
+        DaryHeap::push(self, item);
+    }
+
+    #[inline]
+    fn pop(&mut self) -> Option<T> {
+        DaryHeap::pop(self)
+    }
+
+    #[inline]
+    fn from_vec(items: Vec<T>) -> Self {
+        DaryHeap::from(items)
+    }
+}
+
+impl<T: Ord, const D: usize, const PREFETCH: bool> BenchHeap<T>
+    for AlignedDaryHeap<T, D, MaxComparator, PREFETCH>
 {
-    (0..size).map(|_| random::<T>()).collect()
+    const NAME: &'static str = if PREFETCH {
+        "aligned-prefetch"
+    } else {
+        "aligned"
+    };
+    const ARITY: Option<usize> = Some(D);
+
+    #[inline]
+    fn new() -> Self {
+        AlignedDaryHeap::new()
+    }
+
+    #[inline]
+    fn push(&mut self, item: T) {
+        AlignedDaryHeap::push(self, item);
+    }
+
+    #[inline]
+    fn pop(&mut self) -> Option<T> {
+        AlignedDaryHeap::pop(self)
+    }
 }
 
-const D: usize = 2;
-const TEST_POP: bool = true;
-type TestType = f64;
-type WrappedType = NotNan<TestType>;
+impl<T: BenchItem, const D: usize> BenchHeap<T> for DistHeap<T, D> {
+    const NAME: &'static str = "dist";
+    const ARITY: Option<usize> = Some(D);
+
+    #[inline]
+    fn new() -> Self {
+        DistHeap(DaryHeap::new(), PhantomData)
+    }
+
+    #[inline]
+    fn push(&mut self, item: T) {
+        self.0.push((-(item.key() as f64), 0));
+    }
+
+    #[inline]
+    fn pop(&mut self) -> Option<T> {
+        self.0.pop().map(|(dist, _)| T::from_key(-dist as u32))
+    }
+}
+
+impl<T: Ord, const D: usize> BenchHeap<T> for CrateDaryHeap<T, D> {
+    const NAME: &'static str = "crate";
+    const ARITY: Option<usize> = Some(D);
+
+    #[inline]
+    fn new() -> Self {
+        CrateDaryHeap::new()
+    }
+
+    #[inline]
+    fn push(&mut self, item: T) {
+        CrateDaryHeap::push(self, item);
+    }
+
+    #[inline]
+    fn pop(&mut self) -> Option<T> {
+        CrateDaryHeap::pop(self)
+    }
+
+    #[inline]
+    fn from_vec(items: Vec<T>) -> Self {
+        CrateDaryHeap::from(items)
+    }
+}
+
+impl<T: Ord> BenchHeap<T> for BinaryHeap<T> {
+    const NAME: &'static str = "std";
+    const ARITY: Option<usize> = Some(2);
+
+    #[inline]
+    fn new() -> Self {
+        BinaryHeap::new()
+    }
+
+    #[inline]
+    fn push(&mut self, item: T) {
+        BinaryHeap::push(self, item);
+    }
+
+    #[inline]
+    fn pop(&mut self) -> Option<T> {
+        BinaryHeap::pop(self)
+    }
+
+    #[inline]
+    fn from_vec(items: Vec<T>) -> Self {
+        BinaryHeap::from(items)
+    }
+}
+
+impl<T: Ord> BenchHeap<T> for PairingHeap<T> {
+    const NAME: &'static str = "pairing";
+    const ARITY: Option<usize> = None;
+
+    #[inline]
+    fn new() -> Self {
+        PairingHeap::new()
+    }
+
+    #[inline]
+    fn push(&mut self, item: T) {
+        PairingHeap::push(self, item);
+    }
+
+    #[inline]
+    fn pop(&mut self) -> Option<T> {
+        PairingHeap::pop(self)
+    }
+}
+
+impl<T: Ord> BenchHeap<T> for FibonacciHeap<T> {
+    const NAME: &'static str = "fibonacci";
+    const ARITY: Option<usize> = None;
+
+    #[inline]
+    fn new() -> Self {
+        FibonacciHeap::new()
+    }
+
+    #[inline]
+    fn push(&mut self, item: T) {
+        FibonacciHeap::push(self, item);
+    }
+
+    #[inline]
+    fn pop(&mut self) -> Option<T> {
+        FibonacciHeap::pop(self)
+    }
+}
+
+// 单次运行, 只计入堆操作的时间
+fn run_workload<T: BenchItem, H: BenchHeap<T>>(
+    workload: &str,
+    items: &[T],
+    deltas: &[u32],
+) -> Duration {
+    let size = items.len();
+    match workload {
+        // 全部压入后全部弹出
+        "push-pop" => {
+            let start = Instant::now();
+            let mut heap = H::new();
+            items.iter().for_each(|&item| heap.push(item));
+            while let Some(item) = heap.pop() {
+                black_box(item);
+            }
+            start.elapsed()
+        }
+        // 预先装入一半, 之后每次压入紧跟一次弹出
+        "interleaved" => {
+            let mut heap = H::new();
+            items[..size / 2].iter().for_each(|&item| heap.push(item));
+            let start = Instant::now();
+            for &item in &items[size / 2..] {
+                heap.push(item);
+                black_box(heap.pop());
+            }
+            start.elapsed()
+        }
+        // 类似 Dijkstra: 新压入的键不超过刚弹出的键
+        "monotone" => {
+            let start = Instant::now();
+            let mut heap = H::new();
+            heap.push(T::from_key(u32::MAX));
+            let mut pushed = 1;
+            while let Some(item) = heap.pop() {
+                for _ in 0..FANOUT {
+                    if pushed >= size {
+                        break;
+                    }
+                    heap.push(T::from_key(item.key().saturating_sub(deltas[pushed])));
+                    pushed += 1;
+                }
+                black_box(item);
+            }
+            start.elapsed()
+        }
+        // 建堆后依次弹出得到有序序列
+        "heapsort" => {
+            let items = items.to_vec();
+            let start = Instant::now();
+            let mut heap = H::from_vec(items);
+            let mut sorted = Vec::with_capacity(size);
+            while let Some(item) = heap.pop() {
+                sorted.push(item);
+            }
+            black_box(&sorted);
+            start.elapsed()
+        }
+        _ => unreachable!(),
+    }
+}
+
+// 把运行时的 D 映射到常量泛型参数
+macro_rules! dispatch_arity {
+    ($bench:expr, $d:expr, $heap:ident, $t:ty) => {
+        match $d {
+            2 => $bench.run::<$t, $heap<$t, 2>>(),
+            3 => $bench.run::<$t, $heap<$t, 3>>(),
+            4 => $bench.run::<$t, $heap<$t, 4>>(),
+            8 => $bench.run::<$t, $heap<$t, 8>>(),
+            16 => $bench.run::<$t, $heap<$t, 16>>(),
+            _ => unreachable!(),
+        }
+    };
+}
+
+// 兄弟节点按缓存行对齐的布局, 可选预取孙节点. 兄弟节点组的字节数须整除 64 或是 64 的倍数,
+// 测试的类型都是 2 的幂字节, 只有 D = 3 可能不满足
+macro_rules! dispatch_aligned_arity {
+    ($bench:expr, $d:expr, $prefetch:expr, $t:ty, $name:expr) => {
+        match $d {
+            2 => $bench.run::<$t, AlignedDaryHeap<$t, 2, MaxComparator, $prefetch>>(),
+            3 => {
+                if !<$t>::run_aligned_ternary::<_, $prefetch>($bench) {
+                    println!(
+                        "{:<16} D=3   {:<9} skipped, sibling groups cannot be aligned",
+                        $name,
+                        <$t>::NAME
+                    );
+                }
+            }
+            4 => $bench.run::<$t, AlignedDaryHeap<$t, 4, MaxComparator, $prefetch>>(),
+            8 => $bench.run::<$t, AlignedDaryHeap<$t, 8, MaxComparator, $prefetch>>(),
+            16 => $bench.run::<$t, AlignedDaryHeap<$t, 16, MaxComparator, $prefetch>>(),
+            _ => unreachable!(),
+        }
+    };
+}
+
+struct Bench<'a, W: Write> {
+    size: usize,
+    repeats: usize,
+    workloads: &'a [&'a str],
+    csv: W,
+}
+
+impl<W: Write> Bench<'_, W> {
+    // 每次重复都重新生成随机数据, 每次结果写一行 csv
+    fn run<T: BenchItem, H: BenchHeap<T>>(&mut self) {
+        let mut rng = rand::rng();
+        let arity = H::ARITY.map_or(String::new(), |d| d.to_string());
+        for &workload in self.workloads {
+            let mut times = Vec::with_capacity(self.repeats);
+            for repeat in 0..self.repeats {
+                let items: Vec<T> = (0..self.size).map(|_| T::from_key(rng.random())).collect();
+                let deltas: Vec<u32> = (0..self.size).map(|_| rng.random_range(0..1024)).collect();
+                let time = run_workload::<T, H>(workload, &items, &deltas);
+                writeln!(
+                    self.csv,
+                    "{},{},{},{},{},{},{}",
+                    H::NAME,
+                    arity,
+                    T::NAME,
+                    workload,
+                    self.size,
+                    repeat,
+                    time.as_nanos()
+                )
+                .expect("Failed to write csv");
+                times.push(time);
+            }
+            times.sort_unstable();
+            println!(
+                "{:<16} D={:<3} {:<9} {:<12} median: {:?}",
+                H::NAME,
+                arity,
+                T::NAME,
+                workload,
+                times[times.len() / 2]
+            );
+        }
+    }
+
+    fn run_type<T: BenchItem>(&mut self, impls: &[&str], arities: &[usize]) {
+        for &name in impls {
+            match name {
+                "dary" => {
+                    for &d in arities {
+                        dispatch_arity!(self, d, DaryHeap, T);
+                    }
+                }
+                "aligned" => {
+                    for &d in arities {
+                        dispatch_aligned_arity!(self, d, false, T, name);
+                    }
+                }
+                "aligned-prefetch" => {
+                    for &d in arities {
+                        dispatch_aligned_arity!(self, d, true, T, name);
+                    }
+                }
+                "crate" => {
+                    for &d in arities {
+                        dispatch_arity!(self, d, CrateDaryHeap, T);
+                    }
+                }
+                // 只有 f64 键与原始距离对应
+                "dist" if T::NAME == "f64" => {
+                    for &d in arities {
+                        dispatch_arity!(self, d, DistHeap, T);
+                    }
+                }
+                "dist" => {}
+                "std" => self.run::<T, BinaryHeap<T>>(),
+                "pairing" => self.run::<T, PairingHeap<T>>(),
+                "fibonacci" => self.run::<T, FibonacciHeap<T>>(),
+                _ => unreachable!(),
+            }
+        }
+    }
+}
 
 fn main() {
     let matches = Command::new("dary_heap_bench")
@@ -25,85 +486,113 @@ fn main() {
                 .short('s')
                 .long("size")
                 .value_parser(clap::value_parser!(usize))
-                .default_value("5000"),
+                .default_value("100000"),
+        )
+        .arg(
+            Arg::new("repeats")
+                .short('r')
+                .long("repeats")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .default_value("5"),
+        )
+        .arg(
+            Arg::new("arity")
+                .short('d')
+                .long("arity")
+                .num_args(1..)
+                .value_parser(["2", "3", "4", "8", "16"])
+                .default_values(["2", "3", "4", "8", "16"]),
+        )
+        .arg(
+            Arg::new("type")
+                .short('t')
+                .long("type")
+                .num_args(1..)
+                .value_parser(["u32", "f64", "struct16", "struct64"])
+                .default_values(["u32", "f64", "struct16", "struct64"]),
+        )
+        .arg(
+            Arg::new("workload")
+                .short('w')
+                .long("workload")
+                .num_args(1..)
+                .value_parser(["push-pop", "interleaved", "monotone", "heapsort"])
+                .default_values(["push-pop", "interleaved", "monotone", "heapsort"]),
+        )
+        .arg(
+            Arg::new("impl")
+                .long("impl")
+                .num_args(1..)
+                .value_parser([
+                    "dary",
+                    "aligned",
+                    "aligned-prefetch",
+                    "dist",
+                    "std",
+                    "crate",
+                    "pairing",
+                    "fibonacci",
+                ])
+                .default_values([
+                    "dary",
+                    "aligned",
+                    "aligned-prefetch",
+                    "dist",
+                    "std",
+                    "crate",
+                    "pairing",
+                    "fibonacci",
+                ]),
+        )
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .value_parser(clap::value_parser!(String))
+                .default_value("output/dary_heap_bench/bench.csv"),
         )
         .get_matches();
 
     let size = *matches.get_one::<usize>("size").unwrap();
+    let repeats = *matches.get_one::<u64>("repeats").unwrap() as usize;
+    let arities: Vec<usize> = matches
+        .get_many::<String>("arity")
+        .unwrap()
+        .map(|d| d.parse().unwrap())
+        .collect();
+    let values = |id: &str| -> Vec<&str> {
+        matches
+            .get_many::<String>(id)
+            .unwrap()
+            .map(String::as_str)
+            .collect()
+    };
+    let types = values("type");
+    let workloads = values("workload");
+    let impls = values("impl");
+    let output = Path::new(matches.get_one::<String>("output").unwrap());
 
-    let mut dary_heap = DaryHeap::<WrappedType, D>::new();
-    let mut binary_heap = BinaryHeap::<WrappedType>::new();
-    let mut dary_heap_crate = dary_heap::DaryHeap::<WrappedType, D>::new();
+    if let Some(dir) = output.parent() {
+        fs::create_dir_all(dir).expect("Failed to create output directory");
+    }
+    let mut csv = BufWriter::new(File::create(output).expect("Failed to create csv file"));
+    writeln!(csv, "impl,d,type,workload,size,repeat,time_ns").expect("Failed to write csv");
 
-    println!(
-        "Size: {}, Type: {}",
+    println!("Size: {}, repeats: {}", size, repeats);
+    let mut bench = Bench {
         size,
-        std::any::type_name::<TestType>()
-    );
-
-    // let arr = generate_random_array::<TestType>(size);
-    // let start = Instant::now();
-    // arr.iter().for_each(|&x| binary_heap.push(NotNan::new(x).unwrap()));
-    // let duration = start.elapsed();
-    // println!("std BinaryHeap push time: {:?}", duration);
-
-    // let arr = generate_random_array::<TestType>(size);
-    // let start = Instant::now();
-    // arr.iter().for_each(|&x| dary_heap.push(NotNan::new(x).unwrap()));
-    // let duration = start.elapsed();
-    // println!("My {}-aryHeap push time: {:?}", D, duration);
-
-    // let start = Instant::now();
-    // (0..size).for_each(|_| {binary_heap.pop();});
-    // let duration = start.elapsed();
-    // println!("std BinaryHeap pop time: {:?}", duration);
-
-    // let start = Instant::now();
-    // (0..size).for_each(|_| {dary_heap.pop();});
-    // let duration = start.elapsed();
-    // println!("My {}-aryHeap pop time: {:?}", D, duration);
-
-    let arr = generate_random_array::<TestType>(size);
-    let start = Instant::now();
-    arr.iter()
-        .for_each(|&x| dary_heap.push(NotNan::new(x).unwrap()));
-    let duration = start.elapsed();
-    println!("My {}-aryHeap push time: {:?}", D, duration);
-
-    let arr = generate_random_array::<TestType>(size);
-    let start = Instant::now();
-    arr.iter()
-        .for_each(|&x| binary_heap.push(NotNan::new(x).unwrap()));
-    let duration = start.elapsed();
-    println!("std BinaryHeap push time: {:?}", duration);
-
-    let arr = generate_random_array::<TestType>(size);
-    let start = Instant::now();
-    arr.iter()
-        .for_each(|&x| dary_heap_crate.push(NotNan::new(x).unwrap()));
-    let duration = start.elapsed();
-    println!("Crate {}-aryHeap push time: {:?}", D, duration);
-
-    if TEST_POP {
-        let start = Instant::now();
-        (0..size).for_each(|_| {
-            dary_heap.pop();
-        });
-        let duration = start.elapsed();
-        println!("My {}-aryHeap pop time: {:?}", D, duration);
-
-        let start = Instant::now();
-        (0..size).for_each(|_| {
-            binary_heap.pop();
-        });
-        let duration = start.elapsed();
-        println!("std BinaryHeap pop time: {:?}", duration);
-
-        let start = Instant::now();
-        (0..size).for_each(|_| {
-            dary_heap_crate.pop();
-        });
-        let duration = start.elapsed();
-        println!("Crate {}-aryHeap pop time: {:?}", D, duration);
+        repeats,
+        workloads: &workloads,
+        csv,
+    };
+    for ty in types {
+        match ty {
+            "u32" => bench.run_type::<u32>(&impls, &arities),
+            "f64" => bench.run_type::<NotNan<f64>>(&impls, &arities),
+            "struct16" => bench.run_type::<Bytes16>(&impls, &arities),
+            "struct64" => bench.run_type::<Bytes64>(&impls, &arities),
+            _ => unreachable!(),
+        }
     }
+    bench.csv.flush().expect("Failed to write csv");
 }
@@ -1,6 +1,32 @@
 use algs4::percolation::PercolationStats;
-use algs4::union_find::{QuickUnion, WeightedQuickUnion, WeightedQuickUnionWithPathCompression};
-use clap::{Arg, Command};
+use algs4::union_find::{
+    QuickFind, QuickUnion, QuickUnionByRankWithPathCompression, WeightedQuickUnion,
+    WeightedQuickUnionWithPathCompression, WeightedQuickUnionWithPathHalving,
+    WeightedQuickUnionWithPathSplitting,
+};
+use clap::{Arg, ArgAction, Command};
+
+const ALGORITHMS: [&str; 7] = [
+    "QuickFind",
+    "QuickUnion",
+    "WeightedQuickUnion",
+    "WeightedQuickUnionWithPathCompression",
+    "WeightedQuickUnionWithPathHalving",
+    "WeightedQuickUnionWithPathSplitting",
+    "QuickUnionByRankWithPathCompression",
+];
+
+// 按下标类型和是否计数数组访问选择具体类型
+macro_rules! run {
+    ($uf:ident, $index:expr, $n:expr, $trials:expr, $count:expr) => {
+        match ($index, $count) {
+            ("u32", false) => println!("{}", PercolationStats::<$uf<u32>>::new($n, $trials)),
+            ("u32", true) => println!("{}", PercolationStats::<$uf<u32, true>>::new($n, $trials)),
+            (_, false) => println!("{}", PercolationStats::<$uf<usize>>::new($n, $trials)),
+            (_, true) => println!("{}", PercolationStats::<$uf<usize, true>>::new($n, $trials)),
+        }
+    };
+}
 
 fn main() {
     let matches = Command::new("exp_1")
@@ -22,22 +48,57 @@ fn main() {
             Arg::new("algorithm")
                 .short('a')
                 .long("algorithm")
-                .value_parser(clap::value_parser!(String))
+                .value_parser(clap::builder::PossibleValuesParser::new(
+                    ALGORITHMS.iter().chain(&["all"]),
+                ))
                 .default_value("WeightedQuickUnion"),
         )
+        .arg(
+            Arg::new("index")
+                .short('i')
+                .long("index")
+                .value_parser(["usize", "u32"])
+                .default_value("usize"),
+        )
+        .arg(
+            Arg::new("count")
+                .short('c')
+                .long("count")
+                .action(ArgAction::SetTrue),
+        )
         .get_matches();
 
     let n = *matches.get_one::<usize>("n").unwrap();
     let trials = *matches.get_one::<usize>("trials").unwrap();
     let algorithm = matches.get_one::<String>("algorithm").unwrap().as_str();
-    if algorithm == "WeightedQuickUnion" {
-        println!("{}", PercolationStats::<WeightedQuickUnion>::new(n, trials));
-    } else if algorithm == "WeightedQuickUnionWithPathCompression" {
-        println!(
-            "{}",
-            PercolationStats::<WeightedQuickUnionWithPathCompression>::new(n, trials)
-        );
-    } else if algorithm == "QuickUnion" {
-        println!("{}", PercolationStats::<QuickUnion>::new(n, trials));
+    let index = matches.get_one::<String>("index").unwrap().as_str();
+    let count = matches.get_flag("count");
+    let algorithms = if algorithm == "all" {
+        &ALGORITHMS[..]
+    } else {
+        &[algorithm][..]
+    };
+    for &algorithm in algorithms {
+        match algorithm {
+            "QuickFind" => run!(QuickFind, index, n, trials, count),
+            "QuickUnion" => run!(QuickUnion, index, n, trials, count),
+            "WeightedQuickUnionWithPathCompression" => run!(
+                WeightedQuickUnionWithPathCompression,
+                index,
+                n,
+                trials,
+                count
+            ),
+            "WeightedQuickUnionWithPathHalving" => {
+                run!(WeightedQuickUnionWithPathHalving, index, n, trials, count)
+            }
+            "WeightedQuickUnionWithPathSplitting" => {
+                run!(WeightedQuickUnionWithPathSplitting, index, n, trials, count)
+            }
+            "QuickUnionByRankWithPathCompression" => {
+                run!(QuickUnionByRankWithPathCompression, index, n, trials, count)
+            }
+            _ => run!(WeightedQuickUnion, index, n, trials, count),
+        }
     }
 }
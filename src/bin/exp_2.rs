@@ -87,6 +87,8 @@ where
         (bottom_up_merge_sort, "Bottom-up Mergesort"),
         (quick_sort, "Random Quicksort"),
         (quick_sort_3way, "Quicksort with Dijkstra 3-way Partition)"),
+        (heap_sort::<T, 2>, "Binary Heapsort"),
+        (heap_sort::<T, 4>, "4-ary Heapsort"),
     ];
     let mut durations: Vec<Vec<Vec<Vec<Duration>>>> =
         vec![
@@ -1,8 +1,9 @@
 use algs4::graph::*;
+use algs4::route_render::{self, Route};
 use clap::{Arg, Command};
 use std::fs::File;
 use std::io::Write;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, BufWriter};
 use std::time::{Duration, Instant};
 
 const EARLYSTOP: bool = true;
@@ -25,11 +26,35 @@ fn main() {
                 .value_parser(clap::value_parser!(i32))
                 .default_value("1000"),
         )
+        .arg(
+            Arg::new("render")
+                .short('r')
+                .long("render")
+                .value_parser(["none", "svg", "geojson"])
+                .default_value("none"),
+        )
+        .arg(
+            Arg::new("heap")
+                .long("heap")
+                .value_parser(["dary", "pairing", "fibonacci"])
+                .default_value("dary"),
+        )
         .get_matches();
 
     let input_file = matches.get_one::<String>("input").unwrap();
     let tests = matches.get_one::<i32>("tests").unwrap();
+    let render = matches.get_one::<String>("render").unwrap().as_str();
+    let heap = matches.get_one::<String>("heap").unwrap().as_str();
+
+    println!("Heap: {}", heap);
+    match heap {
+        "pairing" => run::<PairingQueue>(input_file, *tests, render),
+        "fibonacci" => run::<FibonacciQueue>(input_file, *tests, render),
+        _ => run::<DaryQueue>(input_file, *tests, render),
+    }
+}
 
+fn run<Q: DijkstraQueue>(input_file: &str, tests: i32, render: &str) {
     // 读取图的基本信息
     let (n, mut graph) = {
         let file = File::open(input_file).expect("Failed to open file");
@@ -74,7 +99,7 @@ fn main() {
         lines.next(); // 跳过空行
 
         // 构建图
-        let mut graph = Graph::<CALCPATH, HEURISTIC, EARLYSTOP>::new(n, &coord);
+        let mut graph = Graph::<CALCPATH, HEURISTIC, EARLYSTOP, Q>::new(n, &coord);
         for _ in 0..m {
             let line = lines
                 .next()
@@ -120,6 +145,8 @@ fn main() {
         let mut valid_count = 0;
         let output_file = format!("output/exp_3/{}", test_file);
         let mut file = File::create(output_file).expect("Failed to create output file");
+        let mut routes = Vec::new();
+        let mut settled = vec![false; if render != "none" { n } else { 0 }];
 
         for line in reader.lines() {
             let line = line.expect("Failed to read line");
@@ -147,16 +174,43 @@ fn main() {
                 if dist != f64::INFINITY {
                     total_num_nodes += num_nodes;
                 }
-                path.for_each(|node| {
+                let nodes: Vec<_> = path.collect();
+                nodes.iter().for_each(|node| {
                     file.write_all(format!("{} ", node).as_bytes())
                         .expect("Failed to write to file");
                 });
                 file.write_all(format!("{}\n", dist).as_bytes())
                     .expect("Failed to write to file");
+                if render != "none" {
+                    routes.push(Route {
+                        start,
+                        end,
+                        dist,
+                        nodes,
+                    });
+                }
+            }
+            if render != "none" {
+                graph.settled().for_each(|u| settled[u] = true);
             }
             count += 1;
         }
 
+        // 输出路径与已扩展节点的可视化
+        if render != "none" {
+            let settled: Vec<_> = (0..n).filter(|&u| settled[u]).collect();
+            let stem = test_file.trim_end_matches(".txt");
+            let render_file = format!("output/exp_3/{}.{}", stem, render);
+            let mut render_file =
+                BufWriter::new(File::create(render_file).expect("Failed to create render file"));
+            if render == "svg" {
+                route_render::write_svg(&mut render_file, &graph, &routes, &settled)
+            } else {
+                route_render::write_geojson(&mut render_file, &graph, &routes, &settled)
+            }
+            .expect("Failed to write render file");
+        }
+
         println!("File: {}", test_file);
         println!("Total time: {:?}", total_time);
         println!("Average time: {:?}", total_time / count);
@@ -172,7 +226,7 @@ fn main() {
     let mut rng = rand::rng();
     let mut total_time = Duration::new(0, 0);
 
-    for _ in 0..*tests {
+    for _ in 0..tests {
         let start = rand::Rng::random_range(&mut rng, 0..n);
         let end = rand::Rng::random_range(&mut rng, 0..n);
 
@@ -183,5 +237,5 @@ fn main() {
 
     println!("Random tests ({} pairs):", tests);
     println!("Total time: {:?}", total_time);
-    println!("Average time: {:?}", total_time / *tests as u32);
+    println!("Average time: {:?}", total_time / tests as u32);
 }
@@ -0,0 +1,339 @@
+use clap::{Arg, Command};
+use ordered_float::NotNan;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::process;
+
+// 与 exp_3 相同的测试文件列表
+const TEST_FILES: [&str; 7] = [
+    "usa-1.txt",
+    "usa-10.txt",
+    "usa-100long.txt",
+    "usa-100short.txt",
+    "usa-1000long.txt",
+    "usa-5000short.txt",
+    "usa-50000short.txt",
+];
+
+// 参考实现: 朴素 Dijkstra + std BinaryHeap
+struct Reference {
+    adj: Vec<Vec<(usize, f64)>>,
+    dist: Vec<f64>,
+    parent: Vec<usize>,
+    settled: Vec<bool>,
+    changed: Vec<usize>,
+    epsilon: f64,
+}
+
+impl Reference {
+    fn new(adj: Vec<Vec<(usize, f64)>>, epsilon: f64) -> Self {
+        let n = adj.len();
+        Reference {
+            adj,
+            dist: vec![f64::INFINITY; n],
+            parent: vec![usize::MAX; n],
+            settled: vec![false; n],
+            changed: Vec::new(),
+            epsilon,
+        }
+    }
+
+    // 搜索到距离超过终点为止, 使所有可能的等长前驱都已确定
+    fn search(&mut self, start: usize, end: usize) {
+        while let Some(v) = self.changed.pop() {
+            self.dist[v] = f64::INFINITY;
+            self.parent[v] = usize::MAX;
+            self.settled[v] = false;
+        }
+
+        let mut heap = BinaryHeap::new();
+        self.dist[start] = 0.0;
+        self.changed.push(start);
+        heap.push((Reverse(NotNan::new(0.0).unwrap()), start));
+        while let Some((dist, u)) = heap.pop() {
+            let dist = dist.0.into_inner();
+            if self.settled[end] && dist > self.dist[end] + self.tolerance(self.dist[end]) {
+                return;
+            }
+            if self.settled[u] {
+                continue;
+            }
+            self.settled[u] = true;
+            for &(v, weight) in &self.adj[u] {
+                let next_dist = dist + weight;
+                if next_dist < self.dist[v] {
+                    if self.dist[v] == f64::INFINITY {
+                        self.changed.push(v);
+                    }
+                    self.dist[v] = next_dist;
+                    self.parent[v] = u;
+                    heap.push((Reverse(NotNan::new(next_dist).unwrap()), v));
+                }
+            }
+        }
+    }
+
+    // 路径上每个节点都只有一条等长入边时, 最短路唯一
+    // 重合坐标产生的零权边会让结果偏保守
+    fn is_unique(&self, path: &[usize]) -> bool {
+        path.iter().skip(1).all(|&v| {
+            self.adj[v]
+                .iter()
+                .filter(|&&(u, weight)| {
+                    self.settled[u]
+                        && (self.dist[u] + weight - self.dist[v]).abs()
+                            <= self.tolerance(self.dist[v])
+                })
+                .count()
+                == 1
+        })
+    }
+
+    fn path(&self, start: usize, end: usize) -> Vec<usize> {
+        let mut path = vec![end];
+        let mut current = end;
+        while current != start {
+            current = self.parent[current];
+            path.push(current);
+        }
+        path.reverse();
+        path
+    }
+
+    fn edge_weight(&self, u: usize, v: usize) -> Option<f64> {
+        self.adj[u]
+            .iter()
+            .filter(|&&(to, _)| to == v)
+            .map(|&(_, weight)| weight)
+            .reduce(f64::min)
+    }
+
+    #[inline]
+    fn tolerance(&self, dist: f64) -> f64 {
+        self.epsilon * dist.abs().max(1.0)
+    }
+}
+
+fn read_graph(input_file: &str) -> Vec<Vec<(usize, f64)>> {
+    let file = File::open(input_file).expect("Failed to open file");
+    let mut lines = BufReader::new(file).lines();
+
+    let first_line = lines
+        .next()
+        .expect("No first line")
+        .expect("Failed to read first line");
+    let mut parts = first_line.split_whitespace();
+    let n: usize = parts.next().expect("No n").parse().expect("Invalid n");
+    let m: usize = parts.next().expect("No m").parse().expect("Invalid m");
+
+    let mut coord = vec![(0i32, 0i32); n];
+    for _ in 0..n {
+        let line = lines
+            .next()
+            .expect("Missing node line")
+            .expect("Failed to read node line");
+        let parts: Vec<i64> = line
+            .split_whitespace()
+            .map(|x| x.parse().expect("Invalid node line"))
+            .collect();
+        coord[parts[0] as usize] = (parts[1] as i32, parts[2] as i32);
+    }
+
+    lines.next(); // 跳过空行
+
+    // 权重与 Graph::euclidean_dist 的计算方式一致
+    let mut adj = vec![vec![]; n];
+    for _ in 0..m {
+        let line = lines
+            .next()
+            .expect("Missing edge line")
+            .expect("Failed to read edge line");
+        let parts: Vec<usize> = line
+            .split_whitespace()
+            .map(|x| x.parse().expect("Invalid edge line"))
+            .collect();
+        let (u, v) = (parts[0], parts[1]);
+        let (dx, dy) = (coord[u].0 - coord[v].0, coord[u].1 - coord[v].1);
+        let weight = ((dx.pow(2) + dy.pow(2)) as f64).sqrt();
+        adj[u].push((v, weight));
+        adj[v].push((u, weight));
+    }
+    adj
+}
+
+fn main() {
+    let matches = Command::new("exp_3_check")
+        .arg(
+            Arg::new("input")
+                .short('i')
+                .long("input")
+                .value_parser(clap::value_parser!(String))
+                .default_value("dijkstra_bench/usa.txt"),
+        )
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .value_parser(clap::value_parser!(String))
+                .default_value("output/exp_3"),
+        )
+        .arg(
+            Arg::new("epsilon")
+                .short('e')
+                .long("epsilon")
+                .value_parser(clap::value_parser!(f64))
+                .default_value("1e-9"),
+        )
+        .get_matches();
+
+    let input_file = matches.get_one::<String>("input").unwrap();
+    let output_dir = matches.get_one::<String>("output").unwrap();
+    let epsilon = *matches.get_one::<f64>("epsilon").unwrap();
+
+    let mut reference = Reference::new(read_graph(input_file), epsilon);
+    let mut total_mismatches = 0;
+
+    for test_file in TEST_FILES {
+        let queries =
+            File::open(format!("dijkstra_bench/{}", test_file)).expect("Failed to open test file");
+        let output_path = format!("{}/{}", output_dir, test_file);
+        let Ok(output) = File::open(&output_path) else {
+            eprintln!("{}: missing output file", output_path);
+            total_mismatches += 1;
+            continue;
+        };
+        let mut outputs = BufReader::new(output).lines();
+        let mut mismatches = 0;
+        let mut count = 0;
+        let mut truncated = false;
+
+        for (i, query) in BufReader::new(queries).lines().enumerate() {
+            let query = query.expect("Failed to read line");
+            let mut parts = query.split_whitespace();
+            let start: usize = parts
+                .next()
+                .expect("No start node")
+                .parse()
+                .expect("Invalid start node");
+            let end: usize = parts
+                .next()
+                .expect("No end node")
+                .parse()
+                .expect("Invalid end node");
+            let line_no = i + 1;
+            count += 1;
+
+            if truncated {
+                mismatches += 1;
+                continue;
+            }
+            let Some(Ok(line)) = outputs.next() else {
+                eprintln!(
+                    "{}:{}: output ends here, this and later queries are unanswered",
+                    output_path, line_no
+                );
+                truncated = true;
+                mismatches += 1;
+                continue;
+            };
+            let mut values: Vec<&str> = line.split_whitespace().collect();
+            let Some(Ok(dist)) = values.pop().map(str::parse::<f64>) else {
+                eprintln!("{}:{}: malformed output line", output_path, line_no);
+                mismatches += 1;
+                continue;
+            };
+            let Ok(path) = values
+                .iter()
+                .map(|x| x.parse::<usize>())
+                .collect::<Result<Vec<_>, _>>()
+            else {
+                eprintln!("{}:{}: malformed path", output_path, line_no);
+                mismatches += 1;
+                continue;
+            };
+
+            reference.search(start, end);
+            let expected = reference.dist[end];
+
+            if expected == f64::INFINITY || dist == f64::INFINITY {
+                if expected != dist {
+                    eprintln!(
+                        "{}:{}: {} -> {}: distance {} != reference {}",
+                        output_path, line_no, start, end, dist, expected
+                    );
+                    mismatches += 1;
+                }
+                continue;
+            }
+            if (dist - expected).abs() > reference.tolerance(expected) {
+                eprintln!(
+                    "{}:{}: {} -> {}: distance {} != reference {}",
+                    output_path, line_no, start, end, dist, expected
+                );
+                mismatches += 1;
+                continue;
+            }
+
+            // 路径需连通起点与终点, 且长度与报告的距离一致
+            let length = path.windows(2).try_fold(0.0, |length, pair| {
+                reference
+                    .edge_weight(pair[0], pair[1])
+                    .map(|weight| length + weight)
+            });
+            if path.first() != Some(&start) || path.last() != Some(&end) {
+                eprintln!(
+                    "{}:{}: {} -> {}: path does not connect the query nodes",
+                    output_path, line_no, start, end
+                );
+                mismatches += 1;
+                continue;
+            }
+            match length {
+                None => {
+                    eprintln!(
+                        "{}:{}: {} -> {}: path uses a missing edge",
+                        output_path, line_no, start, end
+                    );
+                    mismatches += 1;
+                    continue;
+                }
+                Some(length) if (length - expected).abs() > reference.tolerance(expected) => {
+                    eprintln!(
+                        "{}:{}: {} -> {}: path length {} != reference {}",
+                        output_path, line_no, start, end, length, expected
+                    );
+                    mismatches += 1;
+                    continue;
+                }
+                _ => {}
+            }
+
+            // 只有最短路唯一时才逐点比较
+            let expected_path = reference.path(start, end);
+            if path != expected_path && reference.is_unique(&expected_path) {
+                eprintln!(
+                    "{}:{}: {} -> {}: path differs from the unique shortest path",
+                    output_path, line_no, start, end
+                );
+                mismatches += 1;
+            }
+        }
+
+        if outputs.next().is_some() {
+            eprintln!("{}: more output lines than queries", output_path);
+            mismatches += 1;
+        }
+
+        println!("File: {}", test_file);
+        println!("Queries: {}, mismatches: {}", count, mismatches);
+        total_mismatches += mismatches;
+    }
+
+    if total_mismatches > 0 {
+        println!("{} mismatches", total_mismatches);
+        process::exit(1);
+    }
+    println!("All outputs match the reference");
+}
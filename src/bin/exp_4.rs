@@ -3,13 +3,18 @@ use aho_corasick::automaton::OverlappingState;
 use algs4::aho_corasick_bitmap;
 use algs4::aho_corasick_fixed_vector;
 use algs4::aho_corasick_hashmap;
+use algs4::top_k::TopK;
 use algs4::utils::{Placeholder, Trallocator};
 use std::alloc::System;
+use std::cmp::Reverse;
 use std::fs::{self, File};
 use std::io::{self, BufRead, Write};
 use std::path::Path;
 use std::time::Instant;
 
+// 每个文本报告出现次数最多的词数
+const TOP_WORDS: usize = 10;
+
 #[global_allocator]
 static GLOBAL: Trallocator<System> = Trallocator::new(System);
 // static GLOBAL: System = System;
@@ -233,6 +238,22 @@ fn main() -> io::Result<()> {
             //     build_duration_crate + count_duration_crate,
             //     heap_usage_crate
             // )?;
+
+            // 出现次数最多的词, 次数相同时按词典顺序
+            let mut top_words =
+                TopK::<usize, 4, _>::by_key(TOP_WORDS, |&i: &usize| (counts_bitmap[i], Reverse(i)));
+            top_words.extend((0..patterns.len()).filter(|&i| counts_bitmap[i] > 0));
+            let top_words: Vec<_> = top_words
+                .into_sorted_vec()
+                .into_iter()
+                .map(|i| format!("{} ({})", patterns[i], counts_bitmap[i]))
+                .collect();
+            writeln!(
+                bench_file,
+                "Top {} words: {}",
+                TOP_WORDS,
+                top_words.join(", ")
+            )?;
             writeln!(bench_file, "--------------------------------")?;
         }
     }
@@ -0,0 +1,317 @@
+use algs4::union_find::{
+    AtomicUnionFind, QuickFind, QuickUnion, QuickUnionByRankWithPathCompression, RollbackUnionFind,
+    UnionFind, WeightedQuickUnion, WeightedQuickUnionWithPathCompression,
+    WeightedQuickUnionWithPathHalving, WeightedQuickUnionWithPathSplitting,
+};
+use clap::{Arg, ArgAction, Command};
+use rand::Rng;
+use std::fs;
+use std::hint::black_box;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+const IMPLS: [&str; 9] = [
+    "QuickFind",
+    "QuickUnion",
+    "WeightedQuickUnion",
+    "WeightedQuickUnionWithPathCompression",
+    "WeightedQuickUnionWithPathHalving",
+    "WeightedQuickUnionWithPathSplitting",
+    "QuickUnionByRankWithPathCompression",
+    "RollbackUnionFind",
+    "AtomicUnionFind",
+];
+
+// 一组操作: 先依次合并 unions 中的点对, 再查询 queries 中的点对
+struct Workload {
+    name: String,
+    n: usize,
+    unions: Vec<(usize, usize)>,
+    queries: Vec<(usize, usize)>,
+}
+
+// 读取 algs4 格式的轨迹文件 (tinyUF.txt 等), 以文件名为负载名
+fn read_trace(path: &str) -> Workload {
+    let text = fs::read_to_string(path).expect("Failed to read trace file");
+    let name = Path::new(path).file_stem().unwrap().to_string_lossy();
+    parse_trace(&name, &text).unwrap_or_else(|error| panic!("{}: {}", path, error))
+}
+
+// 首个整数为 n, 其后每行一对整数. 轨迹中的点对先全部合并, 再按原顺序查询一遍
+fn parse_trace(name: &str, text: &str) -> Result<Workload, &'static str> {
+    let numbers = text
+        .split_whitespace()
+        .map(|token| token.parse::<usize>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| "Malformed trace file")?;
+    let (&n, rest) = numbers.split_first().ok_or("Empty trace file")?;
+    if rest.len() % 2 != 0 {
+        return Err("Trace file ends in the middle of a pair");
+    }
+    let pairs: Vec<(usize, usize)> = rest
+        .chunks_exact(2)
+        .map(|pair| (pair[0], pair[1]))
+        .collect();
+    if pairs.iter().any(|&(p, q)| p >= n || q >= n) {
+        return Err("Site out of range in trace file");
+    }
+    Ok(Workload {
+        name: name.to_string(),
+        n,
+        queries: pairs.clone(),
+        unions: pairs,
+    })
+}
+
+// 随机生成的操作, 查询总是均匀随机的点对
+fn generate(generator: &str, n: usize, ops: usize) -> Workload {
+    let mut rng = rand::rng();
+    let mut pairs = |len: usize| -> Vec<(usize, usize)> {
+        (0..len)
+            .map(|_| (rng.random_range(0..n), rng.random_range(0..n)))
+            .collect()
+    };
+    let unions = match generator {
+        // 依次合并相邻的点, 不按大小合并的实现会得到一条长链
+        "chain" => (1..n).take(ops).map(|p| (p - 1, p)).collect(),
+        _ => pairs(ops),
+    };
+    Workload {
+        name: generator.to_string(),
+        n,
+        unions,
+        queries: pairs(ops),
+    }
+}
+
+// 两阶段分别计时, 并记录合并结束时的树高
+fn run<U: UnionFind>(workload: &Workload) -> (Duration, Duration, usize) {
+    let mut uf = U::new(workload.n);
+    let start = Instant::now();
+    workload.unions.iter().for_each(|&(p, q)| {
+        black_box(uf.union(p, q));
+    });
+    let union_time = start.elapsed();
+    let height = uf.height();
+
+    let start = Instant::now();
+    workload.queries.iter().for_each(|&(p, q)| {
+        black_box(uf.connected(p, q));
+    });
+    (union_time, start.elapsed(), height)
+}
+
+struct Bench<'a> {
+    repeats: usize,
+    csv: bool,
+    workload: &'a Workload,
+}
+
+impl Bench<'_> {
+    fn run<U: UnionFind>(&self, name: &str, index: &str) {
+        let mut union_times = Vec::with_capacity(self.repeats);
+        let mut query_times = Vec::with_capacity(self.repeats);
+        let mut height = 0;
+        for _ in 0..self.repeats {
+            let (union_time, query_time, h) = run::<U>(self.workload);
+            union_times.push(union_time);
+            query_times.push(query_time);
+            // 随机链接的实现每次的树高可能不同, 取最大值
+            height = height.max(h);
+        }
+        union_times.sort_unstable();
+        query_times.sort_unstable();
+        let (union_time, query_time) =
+            (union_times[self.repeats / 2], query_times[self.repeats / 2]);
+        if self.csv {
+            println!(
+                "{},{},{},{},{},{},{},{},{}",
+                self.workload.name,
+                name,
+                index,
+                self.workload.n,
+                self.workload.unions.len(),
+                self.workload.queries.len(),
+                union_time.as_nanos(),
+                query_time.as_nanos(),
+                height
+            );
+        } else {
+            println!(
+                "{:<40} {:<6} union median: {:>12?}  connected median: {:>12?}  height: {:>4}",
+                name, index, union_time, query_time, height
+            );
+        }
+    }
+}
+
+// 把运行时的下标类型映射到泛型参数
+macro_rules! dispatch_index {
+    ($bench:expr, $uf:ident, $name:expr, $index:expr) => {
+        match $index {
+            "u32" => $bench.run::<$uf<u32>>($name, $index),
+            _ => $bench.run::<$uf<usize>>($name, $index),
+        }
+    };
+}
+
+fn main() {
+    let matches = Command::new("uf_bench")
+        .arg(
+            Arg::new("n")
+                .short('n')
+                .long("n")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .default_value("1000000"),
+        )
+        .arg(
+            Arg::new("ops")
+                .short('m')
+                .long("ops")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("1000000"),
+        )
+        .arg(
+            Arg::new("repeats")
+                .short('r')
+                .long("repeats")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .default_value("5"),
+        )
+        .arg(
+            Arg::new("impl")
+                .long("impl")
+                .num_args(1..)
+                .value_parser(IMPLS)
+                // QuickFind 与 QuickUnion 在大规模下退化为平方级, 默认不测
+                .default_values(&IMPLS[2..]),
+        )
+        .arg(
+            Arg::new("index")
+                .short('i')
+                .long("index")
+                .num_args(1..)
+                .value_parser(["usize", "u32"])
+                .default_values(["usize", "u32"]),
+        )
+        .arg(
+            Arg::new("trace")
+                .short('t')
+                .long("trace")
+                .num_args(1..)
+                .help("algs4 format trace files, replacing the random generator"),
+        )
+        .arg(
+            Arg::new("gen")
+                .short('g')
+                .long("gen")
+                .value_parser(["uniform", "chain"])
+                .default_value("uniform"),
+        )
+        .arg(Arg::new("csv").long("csv").action(ArgAction::SetTrue))
+        .get_matches();
+
+    let n = *matches.get_one::<u64>("n").unwrap() as usize;
+    let ops = *matches.get_one::<usize>("ops").unwrap();
+    let repeats = *matches.get_one::<u64>("repeats").unwrap() as usize;
+    let csv = matches.get_flag("csv");
+    let impls: Vec<&str> = matches
+        .get_many::<String>("impl")
+        .unwrap()
+        .map(String::as_str)
+        .collect();
+    let indices: Vec<&str> = matches
+        .get_many::<String>("index")
+        .unwrap()
+        .map(String::as_str)
+        .collect();
+
+    // 所有实现使用同一组操作
+    let workloads: Vec<Workload> = match matches.get_many::<String>("trace") {
+        Some(paths) => paths.map(|path| read_trace(path)).collect(),
+        None => vec![generate(matches.get_one::<String>("gen").unwrap(), n, ops)],
+    };
+
+    if csv {
+        println!("workload,impl,index,n,unions,queries,union_ns,connected_ns,height");
+    }
+    for workload in &workloads {
+        if !csv {
+            println!(
+                "workload: {}, n: {}, unions: {}, queries: {}, repeats: {}",
+                workload.name,
+                workload.n,
+                workload.unions.len(),
+                workload.queries.len(),
+                repeats
+            );
+        }
+        let bench = Bench {
+            repeats,
+            csv,
+            workload,
+        };
+        for &name in &impls {
+            // 这两种实现只有 usize 下标
+            match name {
+                "RollbackUnionFind" => bench.run::<RollbackUnionFind>(name, "usize"),
+                "AtomicUnionFind" => bench.run::<AtomicUnionFind>(name, "usize"),
+                _ => run_indexed(&bench, name, &indices),
+            }
+        }
+    }
+}
+
+fn run_indexed(bench: &Bench, name: &str, indices: &[&str]) {
+    for &index in indices {
+        match name {
+            "QuickFind" => dispatch_index!(bench, QuickFind, name, index),
+            "QuickUnion" => dispatch_index!(bench, QuickUnion, name, index),
+            "WeightedQuickUnionWithPathCompression" => {
+                dispatch_index!(bench, WeightedQuickUnionWithPathCompression, name, index)
+            }
+            "WeightedQuickUnionWithPathHalving" => {
+                dispatch_index!(bench, WeightedQuickUnionWithPathHalving, name, index)
+            }
+            "WeightedQuickUnionWithPathSplitting" => {
+                dispatch_index!(bench, WeightedQuickUnionWithPathSplitting, name, index)
+            }
+            "QuickUnionByRankWithPathCompression" => {
+                dispatch_index!(bench, QuickUnionByRankWithPathCompression, name, index)
+            }
+            _ => dispatch_index!(bench, WeightedQuickUnion, name, index),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_trace() {
+        // algs4 tinyUF.txt
+        let text = "10\n4 3\n3 8\n6 5\n9 4\n2 1\n8 9\n5 0\n7 2\n6 1\n1 0\n6 7\n";
+        let workload = parse_trace("tinyUF", text).unwrap();
+        assert_eq!(workload.name, "tinyUF");
+        assert_eq!(workload.n, 10);
+        assert_eq!(workload.unions.len(), 11);
+        assert_eq!(workload.unions[..2], [(4, 3), (3, 8)]);
+        assert_eq!(workload.queries, workload.unions);
+
+        let mut uf: WeightedQuickUnion = UnionFind::new(workload.n);
+        workload.unions.iter().for_each(|&(p, q)| {
+            uf.union(p, q);
+        });
+        assert_eq!(uf.count(), 2);
+    }
+
+    #[test]
+    fn test_parse_trace_errors() {
+        assert!(parse_trace("empty", " \n").is_err_and(|e| e.contains("Empty")));
+        assert!(parse_trace("odd", "10\n4 3\n3").is_err_and(|e| e.contains("middle of a pair")));
+        assert!(parse_trace("range", "10\n4 3\n3 10").is_err_and(|e| e.contains("out of range")));
+        assert!(parse_trace("garbage", "10\n4 x").is_err_and(|e| e.contains("Malformed")));
+        assert!(parse_trace("negative", "10\n4 -3").is_err());
+    }
+}
@@ -0,0 +1,87 @@
+use std::cmp::Ordering;
+
+// Orders heap elements: the greatest element according to `compare` is on top
+pub trait Compare<T> {
+    fn compare(&self, a: &T, b: &T) -> Ordering;
+
+    // `a` belongs above `b`. Override when it is cheaper than a full `compare`.
+    #[inline]
+    fn gt(&self, a: &T, b: &T) -> bool {
+        self.compare(a, b) == Ordering::Greater
+    }
+}
+
+// Max-heap by `Ord`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MaxComparator;
+
+impl<T: Ord> Compare<T> for MaxComparator {
+    #[inline]
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        a.cmp(b)
+    }
+
+    #[inline]
+    fn gt(&self, a: &T, b: &T) -> bool {
+        a > b
+    }
+}
+
+// Min-heap by `Ord`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MinComparator;
+
+impl<T: Ord> Compare<T> for MinComparator {
+    #[inline]
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        b.cmp(a)
+    }
+
+    #[inline]
+    fn gt(&self, a: &T, b: &T) -> bool {
+        a < b
+    }
+}
+
+// Any `Fn(&T, &T) -> Ordering`, the greatest element is on top
+#[derive(Clone, Copy, Debug)]
+pub struct FnComparator<F>(pub F);
+
+impl<T, F: Fn(&T, &T) -> Ordering> Compare<T> for FnComparator<F> {
+    #[inline]
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        (self.0)(a, b)
+    }
+}
+
+// The element with the greatest key is on top
+#[derive(Clone, Copy, Debug)]
+pub struct KeyComparator<F>(pub F);
+
+impl<T, K: Ord, F: Fn(&T) -> K> Compare<T> for KeyComparator<F> {
+    #[inline]
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        (self.0)(a).cmp(&(self.0)(b))
+    }
+
+    #[inline]
+    fn gt(&self, a: &T, b: &T) -> bool {
+        (self.0)(a) > (self.0)(b)
+    }
+}
+
+// Puts the least element according to `C` on top
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Reversed<C>(pub C);
+
+impl<T, C: Compare<T>> Compare<T> for Reversed<C> {
+    #[inline]
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        self.0.compare(b, a)
+    }
+
+    #[inline]
+    fn gt(&self, a: &T, b: &T) -> bool {
+        self.0.gt(b, a)
+    }
+}
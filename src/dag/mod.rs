@@ -0,0 +1,324 @@
+use crate::graph::{DijkstraQueue, Graph};
+
+// Vertices in topological order, or a directed cycle `v, ..., v` if there is none
+pub fn topological_order<
+    const CALCPATH: bool,
+    const HEURISTIC: bool,
+    const EARLYSTOP: bool,
+    Q: DijkstraQueue,
+>(
+    graph: &Graph<CALCPATH, HEURISTIC, EARLYSTOP, Q>,
+) -> Result<Vec<usize>, Vec<usize>> {
+    const WHITE: u8 = 0;
+    const GRAY: u8 = 1;
+    const BLACK: u8 = 2;
+
+    let n = graph.num_nodes();
+    let mut color = vec![WHITE; n];
+    let mut postorder = Vec::with_capacity(n);
+    // Iterative DFS, the stack holds the current path from the root
+    let mut stack = Vec::new();
+
+    for root in 0..n {
+        if color[root] != WHITE {
+            continue;
+        }
+        color[root] = GRAY;
+        stack.push((root, graph.adj(root)));
+        while let Some((u, edges)) = stack.last_mut() {
+            let u = *u;
+            match edges.next() {
+                Some((v, _)) => match color[v] {
+                    WHITE => {
+                        color[v] = GRAY;
+                        stack.push((v, graph.adj(v)));
+                    }
+                    GRAY => {
+                        let from = stack.iter().position(|&(w, _)| w == v).unwrap();
+                        let mut cycle: Vec<_> = stack[from..].iter().map(|&(w, _)| w).collect();
+                        cycle.push(v);
+                        return Err(cycle);
+                    }
+                    _ => {}
+                },
+                None => {
+                    color[u] = BLACK;
+                    postorder.push(u);
+                    stack.pop();
+                }
+            }
+        }
+    }
+
+    postorder.reverse();
+    Ok(postorder)
+}
+
+pub struct AcyclicPaths {
+    source: usize,
+    dist: Vec<f64>,
+    parent: Vec<usize>,
+}
+
+impl AcyclicPaths {
+    #[inline]
+    pub fn shortest<
+        const CALCPATH: bool,
+        const HEURISTIC: bool,
+        const EARLYSTOP: bool,
+        Q: DijkstraQueue,
+    >(
+        graph: &Graph<CALCPATH, HEURISTIC, EARLYSTOP, Q>,
+        source: usize,
+    ) -> Result<Self, Vec<usize>> {
+        Self::build::<false, CALCPATH, HEURISTIC, EARLYSTOP, Q>(graph, source)
+    }
+
+    #[inline]
+    pub fn longest<
+        const CALCPATH: bool,
+        const HEURISTIC: bool,
+        const EARLYSTOP: bool,
+        Q: DijkstraQueue,
+    >(
+        graph: &Graph<CALCPATH, HEURISTIC, EARLYSTOP, Q>,
+        source: usize,
+    ) -> Result<Self, Vec<usize>> {
+        Self::build::<true, CALCPATH, HEURISTIC, EARLYSTOP, Q>(graph, source)
+    }
+
+    fn build<
+        const LONGEST: bool,
+        const CALCPATH: bool,
+        const HEURISTIC: bool,
+        const EARLYSTOP: bool,
+        Q: DijkstraQueue,
+    >(
+        graph: &Graph<CALCPATH, HEURISTIC, EARLYSTOP, Q>,
+        source: usize,
+    ) -> Result<Self, Vec<usize>> {
+        let order = topological_order(graph)?;
+        let n = graph.num_nodes();
+        let unreachable = if LONGEST {
+            f64::NEG_INFINITY
+        } else {
+            f64::INFINITY
+        };
+        let mut dist = vec![unreachable; n];
+        let mut parent: Vec<_> = (0..n).collect();
+        dist[source] = 0.0;
+
+        for u in order.into_iter().skip_while(|&u| u != source) {
+            if dist[u] == unreachable {
+                continue;
+            }
+            for (v, weight) in graph.adj(u) {
+                let next_dist = dist[u] + weight;
+                let better = if LONGEST {
+                    next_dist > dist[v]
+                } else {
+                    next_dist < dist[v]
+                };
+                if better {
+                    dist[v] = next_dist;
+                    parent[v] = u;
+                }
+            }
+        }
+
+        Ok(AcyclicPaths {
+            source,
+            dist,
+            parent,
+        })
+    }
+
+    // Infinite (negative for longest paths) if `v` is unreachable
+    #[inline]
+    pub fn dist_to(&self, v: usize) -> f64 {
+        self.dist[v]
+    }
+
+    #[inline]
+    pub fn has_path_to(&self, v: usize) -> bool {
+        self.dist[v].is_finite()
+    }
+
+    pub fn path_to(&self, v: usize) -> Option<Vec<usize>> {
+        if !self.has_path_to(v) {
+            return None;
+        }
+        let mut path = vec![v];
+        let mut current = v;
+        while current != self.source {
+            current = self.parent[current];
+            path.push(current);
+        }
+        path.reverse();
+        Some(path)
+    }
+}
+
+#[derive(Debug)]
+pub struct Schedule {
+    pub start_times: Vec<f64>,
+    pub finish_time: f64,
+    pub critical_path: Vec<usize>,
+}
+
+// Critical path method: job `i` takes `durations[i]` and must finish before
+// every job in `successors[i]` starts. A precedence cycle is reported by job ids.
+pub fn critical_path(durations: &[f64], successors: &[Vec<usize>]) -> Result<Schedule, Vec<usize>> {
+    assert_eq!(durations.len(), successors.len());
+    // Job i is the edge i -> i + n, the extra two nodes are the global source and sink
+    let n = durations.len();
+    if n == 0 {
+        return Ok(Schedule {
+            start_times: Vec::new(),
+            finish_time: 0.0,
+            critical_path: Vec::new(),
+        });
+    }
+    let source = 2 * n;
+    let sink = 2 * n + 1;
+    let mut graph = Graph::<false, false, false>::without_coords(2 * n + 2);
+    for (i, (&duration, next)) in durations.iter().zip(successors).enumerate() {
+        graph.add_weighted_edge(source, i, 0.0);
+        graph.add_weighted_edge(i, i + n, duration);
+        graph.add_weighted_edge(i + n, sink, 0.0);
+        for &j in next {
+            graph.add_weighted_edge(i + n, j, 0.0);
+        }
+    }
+
+    let paths = AcyclicPaths::longest(&graph, source).map_err(|cycle| {
+        let mut jobs: Vec<_> = cycle.into_iter().map(|u| u % n).collect();
+        jobs.dedup();
+        jobs
+    })?;
+
+    let start_times = (0..n).map(|i| paths.dist_to(i)).collect();
+    let critical_path = paths
+        .path_to(sink)
+        .unwrap()
+        .into_iter()
+        .filter(|&u| u < n)
+        .collect();
+    Ok(Schedule {
+        start_times,
+        finish_time: paths.dist_to(sink),
+        critical_path,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::{DaryQueue, FibonacciQueue};
+
+    const EPSILON: f64 = 1e-9;
+
+    fn tiny_ewdag<Q: DijkstraQueue>() -> Graph<false, false, false, Q> {
+        let mut graph = Graph::without_coords(8);
+        for &(u, v, w) in &[
+            (5, 4, 0.35),
+            (4, 7, 0.37),
+            (5, 7, 0.28),
+            (5, 1, 0.32),
+            (4, 0, 0.38),
+            (0, 2, 0.26),
+            (3, 7, 0.39),
+            (1, 3, 0.29),
+            (7, 2, 0.34),
+            (6, 2, 0.40),
+            (3, 6, 0.52),
+            (6, 0, 0.58),
+            (6, 4, 0.93),
+        ] {
+            graph.add_weighted_edge(u, v, w);
+        }
+        graph
+    }
+
+    #[test]
+    fn test_topological_order() {
+        let graph = tiny_ewdag::<FibonacciQueue>();
+        let order = topological_order(&graph).unwrap();
+        let mut rank = vec![0; order.len()];
+        order.iter().enumerate().for_each(|(i, &u)| rank[u] = i);
+        assert_eq!(order.len(), 8);
+        for (u, v, _) in graph.edges() {
+            assert!(rank[u] < rank[v]);
+        }
+    }
+
+    #[test]
+    fn test_cycle() {
+        let mut graph = tiny_ewdag::<DaryQueue>();
+        graph.add_weighted_edge(2, 1, 1.0);
+        let cycle = topological_order(&graph).unwrap_err();
+        assert_eq!(cycle.first(), cycle.last());
+        assert!(cycle.len() > 2);
+        for pair in cycle.windows(2) {
+            assert!(graph.adj(pair[0]).any(|(v, _)| v == pair[1]));
+        }
+        assert!(AcyclicPaths::shortest(&graph, 5).is_err());
+    }
+
+    #[test]
+    fn test_shortest_and_longest_paths() {
+        let graph = tiny_ewdag::<DaryQueue>();
+        let shortest = AcyclicPaths::shortest(&graph, 5).unwrap();
+        let expected = [0.73, 0.32, 0.62, 0.61, 0.35, 0.0, 1.13, 0.28];
+        for (v, &dist) in expected.iter().enumerate() {
+            assert!((shortest.dist_to(v) - dist).abs() < EPSILON);
+        }
+        assert_eq!(shortest.path_to(2), Some(vec![5, 7, 2]));
+
+        let longest = AcyclicPaths::longest(&graph, 5).unwrap();
+        let expected = [2.44, 0.32, 2.77, 0.61, 2.06, 0.0, 1.13, 2.43];
+        for (v, &dist) in expected.iter().enumerate() {
+            assert!((longest.dist_to(v) - dist).abs() < EPSILON);
+        }
+        assert_eq!(longest.path_to(2), Some(vec![5, 1, 3, 6, 4, 7, 2]));
+
+        let from_zero = AcyclicPaths::shortest(&graph, 0).unwrap();
+        assert!(!from_zero.has_path_to(5));
+        assert_eq!(from_zero.path_to(5), None);
+    }
+
+    #[test]
+    fn test_critical_path() {
+        // algs4 jobsPC.txt
+        let durations = [41.0, 51.0, 50.0, 36.0, 38.0, 45.0, 21.0, 32.0, 32.0, 29.0];
+        let successors = vec![
+            vec![1, 7, 9],
+            vec![2],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![3, 8],
+            vec![3, 8],
+            vec![2],
+            vec![4, 6],
+        ];
+        let schedule = critical_path(&durations, &successors).unwrap();
+        assert_eq!(schedule.finish_time, 173.0);
+        assert_eq!(
+            schedule.start_times,
+            [0.0, 41.0, 123.0, 91.0, 70.0, 0.0, 70.0, 41.0, 91.0, 41.0]
+        );
+        assert_eq!(schedule.critical_path, [0, 9, 6, 8, 2]);
+
+        let mut successors = successors;
+        successors[2].push(9);
+        let cycle = critical_path(&durations, &successors).unwrap_err();
+        assert_eq!(cycle.first(), cycle.last());
+        assert!(cycle.contains(&2) && cycle.contains(&9));
+
+        let schedule = critical_path(&[], &[]).unwrap();
+        assert_eq!(schedule.finish_time, 0.0);
+        assert!(schedule.start_times.is_empty() && schedule.critical_path.is_empty());
+    }
+}
@@ -1,167 +1,588 @@
-use likely_stable::{likely, unlikely};
-use std::{fmt::Debug, ptr};
-#[derive(Debug)]
-pub struct DaryHeap<T, const D: usize> {
+use crate::compare::{Compare, FnComparator, KeyComparator, MaxComparator, MinComparator};
+use likely_stable::unlikely;
+use std::{
+    cmp::Ordering,
+    fmt::Debug,
+    mem::{self, ManuallyDrop},
+    ops::{Deref, DerefMut},
+    ptr, slice, vec,
+};
+#[derive(Clone, Debug)]
+pub struct DaryHeap<T, const D: usize, C = MaxComparator> {
     data: Vec<T>,
+    cmp: C,
     _assertion: AssertD<D>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 struct AssertD<const D: usize>;
 
 impl<const D: usize> AssertD<D> {
     const _ASSERTION: () = assert!(D >= 2, "D must be at least 2");
 }
 
-impl<T: Ord, const D: usize> DaryHeap<T, D> {
+impl<T, const D: usize, C: Compare<T> + Default> DaryHeap<T, D, C> {
     #[inline]
     pub fn new() -> Self {
-        DaryHeap {
-            data: Vec::new(),
-            _assertion: AssertD::<D>,
-        }
+        Self::with_comparator(C::default())
     }
 
     #[inline]
     pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_comparator(capacity, C::default())
+    }
+}
+
+impl<T: Ord, const D: usize> DaryHeap<T, D, MinComparator> {
+    #[inline]
+    pub fn new_min() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const D: usize, F: Fn(&T, &T) -> Ordering> DaryHeap<T, D, FnComparator<F>> {
+    #[inline]
+    pub fn new_by(f: F) -> Self {
+        Self::with_comparator(FnComparator(f))
+    }
+}
+
+impl<T, const D: usize, K: Ord, F: Fn(&T) -> K> DaryHeap<T, D, KeyComparator<F>> {
+    #[inline]
+    pub fn new_by_key(f: F) -> Self {
+        Self::with_comparator(KeyComparator(f))
+    }
+}
+
+impl<T, const D: usize, C: Compare<T>> DaryHeap<T, D, C> {
+    #[inline]
+    pub fn with_comparator(cmp: C) -> Self {
+        Self::with_capacity_and_comparator(0, cmp)
+    }
+
+    #[inline]
+    pub fn with_capacity_and_comparator(capacity: usize, cmp: C) -> Self {
         DaryHeap {
             data: Vec::with_capacity(capacity),
+            cmp,
             _assertion: AssertD::<D>,
         }
     }
 
+    // O(n) heap construction
+    #[inline]
+    pub fn from_vec_with_comparator(data: Vec<T>, cmp: C) -> Self {
+        let mut heap = DaryHeap {
+            data,
+            cmp,
+            _assertion: AssertD::<D>,
+        };
+        heap.rebuild();
+        heap
+    }
+
+    #[inline]
+    pub fn comparator(&self) -> &C {
+        &self.cmp
+    }
+
     #[inline]
     pub fn peek(&self) -> Option<&T> {
         self.data.first()
     }
 
+    // Panics if an element belongs above its parent. cargo-fuzz builds with
+    // optimizations and passes `--cfg fuzzing`
+    #[cfg(any(test, debug_assertions, fuzzing))]
+    pub fn check_invariants(&self) {
+        for pos in 1..self.data.len() {
+            let parent = (pos - 1) / D;
+            assert!(
+                !self.cmp.gt(&self.data[pos], &self.data[parent]),
+                "element {} belongs above its parent {}",
+                pos,
+                parent
+            );
+        }
+    }
+
+    // The heap is restored when the guard is dropped, if the top was modified
+    #[inline]
+    pub fn peek_mut(&mut self) -> Option<PeekMut<'_, T, D, C>> {
+        if self.data.is_empty() {
+            None
+        } else {
+            Some(PeekMut {
+                heap: self,
+                sift: false,
+            })
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    // Arbitrary order
+    #[inline]
+    pub fn iter(&self) -> slice::Iter<'_, T> {
+        self.data.iter()
+    }
+
+    // Arbitrary order, the heap is empty afterwards
+    #[inline]
+    pub fn drain(&mut self) -> vec::Drain<'_, T> {
+        self.data.drain(..)
+    }
+
+    #[inline]
+    pub fn into_vec(self) -> Vec<T> {
+        self.data
+    }
+
+    // Ascending order according to the comparator
+    pub fn into_sorted_vec(mut self) -> Vec<T> {
+        let mut end = self.data.len();
+        while end > 1 {
+            end -= 1;
+            self.data.swap(0, end);
+            // SAFETY: 0 < end <= self.data.len()
+            unsafe {
+                self.sift_down_range(0, end);
+            }
+        }
+        self.into_vec()
+    }
+
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, f: F) {
+        let len = self.data.len();
+        self.data.retain(f);
+        if self.data.len() < len {
+            self.rebuild();
+        }
+    }
+
+    // Moves all elements of `other` into `self`, leaving `other` empty
+    pub fn append(&mut self, other: &mut Self) {
+        if self.data.len() < other.data.len() {
+            mem::swap(self, other);
+        }
+        let start = self.data.len();
+        self.data.append(&mut other.data);
+        self.rebuild_tail(start);
+    }
+
     #[inline]
     pub fn push(&mut self, item: T) {
-        if unlikely(self.data.len() == 0) {
-            self.data.push(item);
+        let old_len = self.data.len();
+        self.data.push(item);
+        // SAFETY: old_len < self.data.len()
+        unsafe {
+            self.sift_up(0, old_len);
+        }
+    }
+
+    #[inline]
+    pub fn pop(&mut self) -> Option<T> {
+        let item = self.data.pop()?;
+        if unlikely(self.data.is_empty()) {
+            return Some(item);
+        }
+        // SAFETY: the heap is not empty, and the moved-out root is refilled
+        // before anything can panic
+        unsafe {
+            let root = ptr::read(self.data.as_ptr());
+            self.sift_down_to_bottom(0, item);
+            Some(root)
+        }
+    }
+
+    // SAFETY: start <= pos < self.data.len()
+    #[inline]
+    unsafe fn sift_up(&mut self, start: usize, pos: usize) -> usize {
+        unsafe { sift_up::<_, _, D>(&mut self.data, &self.cmp, start, pos) }
+    }
+
+    // SAFETY: pos < end <= self.data.len()
+    #[inline]
+    unsafe fn sift_down_range(&mut self, pos: usize, end: usize) -> usize {
+        unsafe { sift_down_range::<_, _, D>(&mut self.data, &self.cmp, pos, end) }
+    }
+
+    #[inline]
+    fn rebuild(&mut self) {
+        rebuild::<_, _, D>(&mut self.data, &self.cmp);
+    }
+
+    // Restores the heap after elements were appended from index `start` on,
+    // either by sifting each of them up or by rebuilding, whichever is cheaper
+    fn rebuild_tail(&mut self, start: usize) {
+        let len = self.data.len();
+        if start == len {
             return;
+        }
+        let tail_len = len - start;
+        let log_start = (usize::BITS - start.leading_zeros()) as usize;
+        if start < tail_len || 2 * len < tail_len * log_start {
+            self.rebuild();
         } else {
-            let mut child = self.data.len();
-            let mut parent = (child - 1) / D;
-            if unsafe { item <= *self.get(parent) } {
-                self.data.push(item);
-                return;
-            }
-            self.data.push(unsafe { ptr::read(self.get(parent)) });
-            child = parent;
-            while child > 0 {
-                parent = (child - 1) / D;
-                if unsafe { item <= *self.get(parent) } {
-                    unsafe {
-                        *self.get_mut(child) = item;
-                    }
-                    return;
-                }
+            for pos in start..len {
+                // SAFETY: pos < len
                 unsafe {
-                    ptr::copy_nonoverlapping(
-                        self.data.as_ptr().add(parent),
-                        self.data.as_mut_ptr().add(child),
-                        1,
-                    );
+                    self.sift_up(0, pos);
                 }
-                child = parent;
             }
+        }
+    }
+
+    // SAFETY: pos < self.data.len() and the element at pos has been moved out,
+    // `item` takes its place
+    #[inline]
+    unsafe fn sift_down_to_bottom(&mut self, pos: usize, item: T) {
+        unsafe { sift_down_to_bottom::<_, _, D, false>(&mut self.data, &self.cmp, pos, item) }
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.data.clear();
+    }
+}
+
+impl<T, const D: usize, C: Compare<T> + Default> Default for DaryHeap<T, D, C> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const D: usize, C: Compare<T> + Default> From<Vec<T>> for DaryHeap<T, D, C> {
+    #[inline]
+    fn from(data: Vec<T>) -> Self {
+        Self::from_vec_with_comparator(data, C::default())
+    }
+}
+
+impl<T, const D: usize, C> From<DaryHeap<T, D, C>> for Vec<T> {
+    #[inline]
+    fn from(heap: DaryHeap<T, D, C>) -> Self {
+        heap.data
+    }
+}
+
+impl<T, const D: usize, C: Compare<T> + Default> FromIterator<T> for DaryHeap<T, D, C> {
+    #[inline]
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::from(iter.into_iter().collect::<Vec<_>>())
+    }
+}
+
+impl<T, const D: usize, C: Compare<T>> Extend<T> for DaryHeap<T, D, C> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let start = self.data.len();
+        self.data.extend(iter);
+        self.rebuild_tail(start);
+    }
+}
+
+impl<T, const D: usize, C> IntoIterator for DaryHeap<T, D, C> {
+    type Item = T;
+    type IntoIter = vec::IntoIter<T>;
+
+    // Arbitrary order
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.data.into_iter()
+    }
+}
+
+impl<'a, T, const D: usize, C> IntoIterator for &'a DaryHeap<T, D, C> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.data.iter()
+    }
+}
+
+pub struct PeekMut<'a, T, const D: usize, C: Compare<T>> {
+    heap: &'a mut DaryHeap<T, D, C>,
+    sift: bool,
+}
+
+impl<T, const D: usize, C: Compare<T>> PeekMut<'_, T, D, C> {
+    #[inline]
+    pub fn pop(mut this: Self) -> T {
+        this.sift = false;
+        this.heap.pop().unwrap()
+    }
+}
+
+impl<T, const D: usize, C: Compare<T>> Deref for PeekMut<'_, T, D, C> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        // SAFETY: PeekMut is only created for non-empty heaps
+        unsafe { self.heap.data.get_unchecked(0) }
+    }
+}
+
+impl<T, const D: usize, C: Compare<T>> DerefMut for PeekMut<'_, T, D, C> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        self.sift = true;
+        // SAFETY: PeekMut is only created for non-empty heaps
+        unsafe { self.heap.data.get_unchecked_mut(0) }
+    }
+}
+
+impl<T, const D: usize, C: Compare<T>> Drop for PeekMut<'_, T, D, C> {
+    #[inline]
+    fn drop(&mut self) {
+        if self.sift {
+            let len = self.heap.data.len();
+            // SAFETY: the heap is not empty
             unsafe {
-                *self.get_mut(child) = item;
+                self.heap.sift_down_range(0, len);
             }
         }
     }
+}
 
-    #[inline]
-    pub fn pop(&mut self) -> Option<T> {
-        if unlikely(self.data.len() == 0) {
-            return None;
-        } else {
-            let last_idx = self.data.len() - 1;
-            let ret = unsafe { ptr::read(self.data.as_ptr()) };
-            if likely(last_idx != 0) {
-                let item = unsafe { ptr::read(self.get(last_idx)) };
-                let mut parent = 0;
-                let mut child = 1;
-                while child <= last_idx.saturating_sub(D) {
-                    if D == 2 {
-                        child += unsafe { self.get(child + 1) > self.get(child) } as usize;
-                    } else {
-                        for i in (child + 1)..(child + D) {
-                            if unsafe { self.get(i) > self.get(child) } {
-                                child = i;
-                            }
-                        }
-                    }
+// The sift routines work on any slice laid out as a D-ary heap, the children
+// of i being i * D + 1 ..= i * D + D. `aligned_dary_heap` shares them.
 
-                    unsafe {
-                        ptr::copy_nonoverlapping(
-                            self.data.as_ptr().add(child),
-                            self.data.as_mut_ptr().add(parent),
-                            1,
-                        );
-                    }
-                    parent = child;
-                    child = parent * D + 1;
-                }
-                if unlikely(child < last_idx) {
-                    if D > 2 {
-                        for i in (child + 1)..last_idx {
-                            if unsafe { self.get(i) > self.get(child) } {
-                                child = i;
-                            }
-                        }
-                    }
+// SAFETY: start <= pos < data.len()
+#[inline]
+pub(crate) unsafe fn sift_up<T, C: Compare<T>, const D: usize>(
+    data: &mut [T],
+    cmp: &C,
+    start: usize,
+    pos: usize,
+) -> usize {
+    let mut hole = unsafe { Hole::new(data, pos) };
+    while hole.pos() > start {
+        let parent = (hole.pos() - 1) / D;
+        if !cmp.gt(hole.element(), unsafe { hole.get(parent) }) {
+            break;
+        }
+        unsafe {
+            hole.move_to(parent);
+        }
+    }
+    hole.pos()
+}
 
-                    unsafe {
-                        ptr::copy_nonoverlapping(
-                            self.data.as_ptr().add(child),
-                            self.data.as_mut_ptr().add(parent),
-                            1,
-                        );
-                    }
-                    parent = child;
-                }
+// Stops as soon as no child belongs above the element
+// SAFETY: pos < end <= data.len()
+#[inline]
+pub(crate) unsafe fn sift_down_range<T, C: Compare<T>, const D: usize>(
+    data: &mut [T],
+    cmp: &C,
+    pos: usize,
+    end: usize,
+) -> usize {
+    let mut hole = unsafe { Hole::new(data, pos) };
+    let mut child = hole.pos() * D + 1;
+    while child < end {
+        for i in (child + 1)..(child + D).min(end) {
+            if unsafe { cmp.gt(hole.get(i), hole.get(child)) } {
+                child = i;
+            }
+        }
+        if !cmp.gt(unsafe { hole.get(child) }, hole.element()) {
+            break;
+        }
+        unsafe {
+            hole.move_to(child);
+        }
+        child = hole.pos() * D + 1;
+    }
+    hole.pos()
+}
 
-                while parent > 0 {
-                    let parent_parent = (parent - 1) / D;
-                    if unsafe { item <= *self.get(parent_parent) } {
-                        break;
-                    }
-                    unsafe {
-                        ptr::copy_nonoverlapping(
-                            self.data.as_ptr().add(parent_parent),
-                            self.data.as_mut_ptr().add(parent),
-                            1,
-                        );
-                    }
-                    parent = parent_parent;
-                }
+// Floyd's O(n) heap construction
+pub(crate) fn rebuild<T, C: Compare<T>, const D: usize>(data: &mut [T], cmp: &C) {
+    let len = data.len();
+    if len < 2 {
+        return;
+    }
+    for pos in (0..=(len - 2) / D).rev() {
+        // SAFETY: pos < len
+        unsafe {
+            sift_down_range::<T, C, D>(data, cmp, pos, len);
+        }
+    }
+}
 
-                unsafe {
-                    *self.get_mut(parent) = item;
+// Moves the hole all the way down, then sifts the element back up. Fewer
+// comparisons than stopping early, since the last element is usually small.
+// With PREFETCH, the sibling groups of the grandchildren are requested while
+// the children are compared.
+// SAFETY: pos < data.len() and the element at pos has been moved out, `item`
+// takes its place
+#[inline]
+pub(crate) unsafe fn sift_down_to_bottom<T, C: Compare<T>, const D: usize, const PREFETCH: bool>(
+    data: &mut [T],
+    cmp: &C,
+    pos: usize,
+    item: T,
+) {
+    let end = data.len();
+    let start = pos;
+    let base = data.as_ptr();
+    let mut hole = unsafe { Hole::with_element(data, pos, item) };
+    let mut child = hole.pos() * D + 1;
+
+    // All D children exist
+    while child <= end.saturating_sub(D) {
+        if PREFETCH {
+            let grandchild = child * D + 1;
+            for j in 0..D {
+                let group = grandchild + j * D;
+                if group >= end {
+                    break;
                 }
+                prefetch(base.wrapping_add(group));
             }
-            unsafe {
-                self.data.set_len(last_idx);
+        }
+        if D == 2 {
+            child += unsafe { cmp.gt(hole.get(child + 1), hole.get(child)) } as usize;
+        } else {
+            for i in (child + 1)..(child + D) {
+                if unsafe { cmp.gt(hole.get(i), hole.get(child)) } {
+                    child = i;
+                }
+            }
+        }
+        unsafe {
+            hole.move_to(child);
+        }
+        child = hole.pos() * D + 1;
+    }
+
+    // Last parent with fewer than D children
+    if unlikely(child < end) {
+        if D > 2 {
+            for i in (child + 1)..end {
+                if unsafe { cmp.gt(hole.get(i), hole.get(child)) } {
+                    child = i;
+                }
             }
-            Some(ret)
+        }
+        unsafe {
+            hole.move_to(child);
         }
     }
 
+    while hole.pos() > start {
+        let parent = (hole.pos() - 1) / D;
+        if !cmp.gt(hole.element(), unsafe { hole.get(parent) }) {
+            break;
+        }
+        unsafe {
+            hole.move_to(parent);
+        }
+    }
+}
+
+#[inline(always)]
+fn prefetch<T>(ptr: *const T) {
+    #[cfg(all(target_arch = "x86_64", not(miri)))]
+    // SAFETY: prefetching is a hint and never faults, whatever the address
+    unsafe {
+        use std::arch::x86_64::{_MM_HINT_T0, _mm_prefetch};
+        _mm_prefetch::<_MM_HINT_T0>(ptr as *const i8);
+    }
+    #[cfg(not(all(target_arch = "x86_64", not(miri))))]
+    let _ = ptr;
+}
+
+// A slot whose element has been moved out. Dropping the hole writes the
+// element back, so a panicking comparison never leaves the heap with a
+// duplicated or missing element.
+pub(crate) struct Hole<'a, T> {
+    data: &'a mut [T],
+    elt: ManuallyDrop<T>,
+    pos: usize,
+}
+
+impl<'a, T> Hole<'a, T> {
+    // SAFETY: pos < data.len()
     #[inline]
-    pub fn clear(&mut self) {
-        self.data.clear();
+    pub(crate) unsafe fn new(data: &'a mut [T], pos: usize) -> Self {
+        let elt = unsafe { ptr::read(data.get_unchecked(pos)) };
+        Hole {
+            data,
+            elt: ManuallyDrop::new(elt),
+            pos,
+        }
+    }
+
+    // SAFETY: pos < data.len() and the element at pos has been moved out
+    #[inline]
+    pub(crate) unsafe fn with_element(data: &'a mut [T], pos: usize, elt: T) -> Self {
+        Hole {
+            data,
+            elt: ManuallyDrop::new(elt),
+            pos,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn pos(&self) -> usize {
+        self.pos
+    }
+
+    #[inline]
+    pub(crate) fn element(&self) -> &T {
+        &self.elt
+    }
+
+    // SAFETY: index < data.len() and index != pos
+    #[inline]
+    pub(crate) unsafe fn get(&self, index: usize) -> &T {
+        debug_assert!(index != self.pos);
+        unsafe { self.data.get_unchecked(index) }
     }
 
+    // SAFETY: index < data.len() and index != pos
     #[inline]
-    unsafe fn get(&self, idx: usize) -> &T {
-        unsafe { self.data.get_unchecked(idx) }
+    pub(crate) unsafe fn move_to(&mut self, index: usize) {
+        debug_assert!(index != self.pos);
+        unsafe {
+            let ptr = self.data.as_mut_ptr();
+            ptr::copy_nonoverlapping(ptr.add(index), ptr.add(self.pos), 1);
+        }
+        self.pos = index;
     }
 
+    // Exchanges the held element with the one at `index`
+    // SAFETY: index < data.len() and index != pos
     #[inline]
-    unsafe fn get_mut(&mut self, idx: usize) -> &mut T {
-        unsafe { self.data.get_unchecked_mut(idx) }
+    pub(crate) unsafe fn swap_with(&mut self, index: usize) {
+        debug_assert!(index != self.pos);
+        unsafe {
+            ptr::swap(&mut *self.elt, self.data.get_unchecked_mut(index));
+        }
+    }
+}
+
+impl<T> Drop for Hole<'_, T> {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            let pos = self.pos;
+            ptr::copy_nonoverlapping(&*self.elt, self.data.get_unchecked_mut(pos), 1);
+        }
     }
 }
 
@@ -169,6 +590,11 @@ impl<T: Ord, const D: usize> DaryHeap<T, D> {
 mod tests {
     use super::*;
     use rand::Rng;
+    use std::cell::{Cell, RefCell};
+    use std::cmp::Ordering;
+    use std::collections::BinaryHeap;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::rc::Rc;
 
     #[test]
     fn test_random_operations() {
@@ -176,7 +602,7 @@ mod tests {
         let mut heap: DaryHeap<i32, 2> = DaryHeap::new();
         let mut vec = Vec::new();
 
-        (0..1000000).for_each(|_| {
+        (0..if cfg!(miri) { 1000 } else { 1000000 }).for_each(|_| {
             let num = rng.random_range(i32::MIN..i32::MAX);
             heap.push(num);
             vec.push(num);
@@ -188,4 +614,337 @@ mod tests {
         });
         assert_eq!(heap.pop(), None);
     }
+
+    // Records how often each id is dropped, and panics in `cmp` once the
+    // shared budget of comparisons runs out
+    struct Tracked {
+        id: usize,
+        key: u32,
+        drops: Rc<RefCell<Vec<usize>>>,
+        budget: Rc<Cell<usize>>,
+        _boxed: Box<u32>,
+    }
+
+    impl Drop for Tracked {
+        fn drop(&mut self) {
+            self.drops.borrow_mut()[self.id] += 1;
+        }
+    }
+
+    impl Ord for Tracked {
+        fn cmp(&self, other: &Self) -> Ordering {
+            let budget = self.budget.get();
+            if budget == 0 {
+                panic!("comparison budget exhausted");
+            }
+            self.budget.set(budget - 1);
+            self.key.cmp(&other.key)
+        }
+    }
+
+    impl PartialOrd for Tracked {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl PartialEq for Tracked {
+        fn eq(&self, other: &Self) -> bool {
+            self.key == other.key
+        }
+    }
+
+    impl Eq for Tracked {}
+
+    fn check_panic_safety<const D: usize>() {
+        const N: usize = 40;
+        let mut rng = rand::rng();
+        for budget in 0..32 {
+            let drops = Rc::new(RefCell::new(vec![0; N + 1]));
+            let unlimited = Rc::new(Cell::new(usize::MAX));
+            let mut heap = DaryHeap::<Tracked, D>::new();
+            for id in 0..N {
+                heap.push(Tracked {
+                    id,
+                    key: rng.random_range(0..16),
+                    drops: drops.clone(),
+                    budget: unlimited.clone(),
+                    _boxed: Box::new(id as u32),
+                });
+            }
+
+            unlimited.set(budget);
+            let extra = Tracked {
+                id: N,
+                key: rng.random_range(0..16),
+                drops: drops.clone(),
+                budget: unlimited.clone(),
+                _boxed: Box::new(N as u32),
+            };
+            let pushed = panic::catch_unwind(AssertUnwindSafe(|| heap.push(extra)));
+            assert_eq!(heap.data.len(), N + 1);
+            if pushed.is_ok() {
+                let _ = panic::catch_unwind(AssertUnwindSafe(|| drop(heap.pop())));
+                assert_eq!(heap.data.len(), N);
+                // Whether or not `pop` panicked, the removed root is dropped exactly once
+                assert_eq!(drops.borrow().iter().sum::<usize>(), 1);
+            }
+
+            // Every element is still owned exactly once
+            let mut seen = [false; N + 1];
+            for item in &heap.data {
+                assert!(!seen[item.id]);
+                seen[item.id] = true;
+            }
+            unlimited.set(usize::MAX);
+            drop(heap);
+            assert!(drops.borrow().iter().all(|&count| count == 1));
+        }
+    }
+
+    #[test]
+    fn test_panic_safety() {
+        check_panic_safety::<2>();
+        check_panic_safety::<3>();
+        check_panic_safety::<4>();
+        check_panic_safety::<8>();
+    }
+
+    #[test]
+    fn test_drop_count() {
+        let drops = Rc::new(RefCell::new(vec![0; 100]));
+        let budget = Rc::new(Cell::new(usize::MAX));
+        let mut heap = DaryHeap::<Tracked, 4>::new();
+        for id in 0..100 {
+            heap.push(Tracked {
+                id,
+                key: (id * 37 % 101) as u32,
+                drops: drops.clone(),
+                budget: budget.clone(),
+                _boxed: Box::new(id as u32),
+            });
+        }
+
+        let mut last = u32::MAX;
+        for _ in 0..30 {
+            let item = heap.pop().unwrap();
+            assert!(item.key <= last);
+            last = item.key;
+        }
+        assert_eq!(drops.borrow().iter().sum::<usize>(), 30);
+
+        heap.clear();
+        assert_eq!(heap.pop().map(|item| item.id), None);
+        assert!(drops.borrow().iter().all(|&count| count == 1));
+    }
+
+    fn drain_sorted<T, const D: usize, C: Compare<T>>(heap: &mut DaryHeap<T, D, C>) -> Vec<T> {
+        std::iter::from_fn(|| heap.pop()).collect()
+    }
+
+    fn check_collection_api<const D: usize>() {
+        let mut rng = rand::rng();
+        for len in [0, 1, 2, D, D + 1, 100, 1000] {
+            let values: Vec<i32> = (0..len).map(|_| rng.random_range(0..50)).collect();
+            let expected = BinaryHeap::from(values.clone()).into_sorted_vec();
+
+            let heap = DaryHeap::<_, D>::from(values.clone());
+            assert_eq!(heap.len(), len);
+            assert_eq!(heap.is_empty(), len == 0);
+            assert_eq!(heap.peek(), expected.last());
+            let mut items: Vec<_> = heap.iter().copied().collect();
+            items.sort_unstable();
+            assert_eq!(items, expected);
+            assert_eq!(heap.clone().into_sorted_vec(), expected);
+
+            let mut heap: DaryHeap<_, D> = values.iter().copied().collect();
+            let mut sorted = drain_sorted(&mut heap);
+            sorted.reverse();
+            assert_eq!(sorted, expected);
+
+            // Small and large tails take the two different rebuild paths
+            for split in [0, len / 10, len / 2, len] {
+                let mut heap = DaryHeap::<_, D>::from(values[..split].to_vec());
+                heap.extend(values[split..].iter().copied());
+                assert_eq!(heap.into_sorted_vec(), expected);
+
+                let mut heap = DaryHeap::<_, D>::from(values[..split].to_vec());
+                let mut other = DaryHeap::<_, D>::from(values[split..].to_vec());
+                heap.append(&mut other);
+                assert!(other.is_empty());
+                assert_eq!(heap.into_sorted_vec(), expected);
+            }
+
+            let mut heap = DaryHeap::<_, D>::from(values.clone());
+            let mut std_heap = BinaryHeap::from(values.clone());
+            heap.retain(|&x| x % 3 != 0);
+            std_heap.retain(|&x| x % 3 != 0);
+            assert_eq!(heap.into_sorted_vec(), std_heap.into_sorted_vec());
+
+            let mut heap = DaryHeap::<_, D>::from(values.clone());
+            let mut drained: Vec<_> = heap.drain().collect();
+            drained.sort_unstable();
+            assert_eq!(drained, expected);
+            assert!(heap.is_empty() && heap.peek().is_none());
+
+            let mut items = DaryHeap::<_, D>::from(values.clone()).into_vec();
+            items.sort_unstable();
+            assert_eq!(items, expected);
+        }
+    }
+
+    fn check_peek_mut<const D: usize>() {
+        let mut rng = rand::rng();
+        let values: Vec<i32> = (0..500).map(|_| rng.random_range(0..1000)).collect();
+        let mut heap = DaryHeap::<_, D>::from(values.clone());
+        let mut std_heap = BinaryHeap::from(values);
+        for _ in 0..300 {
+            let delta = rng.random_range(-1000..10);
+            match rng.random_range(0..3) {
+                0 => {
+                    *heap.peek_mut().unwrap() += delta;
+                    *std_heap.peek_mut().unwrap() += delta;
+                }
+                1 => {
+                    // Reading through the guard leaves the heap as it is
+                    assert_eq!(*heap.peek_mut().unwrap(), *std_heap.peek().unwrap());
+                }
+                _ => {
+                    let top = PeekMut::pop(heap.peek_mut().unwrap());
+                    assert_eq!(Some(top), std_heap.pop());
+                }
+            }
+            assert_eq!(heap.peek(), std_heap.peek());
+        }
+        assert_eq!(heap.into_sorted_vec(), std_heap.into_sorted_vec());
+        assert!(DaryHeap::<i32, D>::default().peek_mut().is_none());
+    }
+
+    #[test]
+    fn test_collection_api() {
+        check_collection_api::<2>();
+        check_collection_api::<3>();
+        check_collection_api::<4>();
+        check_collection_api::<8>();
+    }
+
+    #[test]
+    fn test_peek_mut() {
+        check_peek_mut::<2>();
+        check_peek_mut::<3>();
+        check_peek_mut::<4>();
+        check_peek_mut::<8>();
+    }
+
+    #[test]
+    fn test_comparators() {
+        let mut rng = rand::rng();
+        let values: Vec<i32> = (0..1000).map(|_| rng.random_range(-500..500)).collect();
+        let mut ascending = values.clone();
+        ascending.sort_unstable();
+
+        let mut heap = DaryHeap::<_, 4, _>::new_min();
+        heap.extend(values.iter().copied());
+        assert_eq!(drain_sorted(&mut heap), ascending);
+
+        let mut heap = DaryHeap::<_, 3, _>::new_by_key(|x: &i32| x.abs());
+        values.iter().for_each(|&x| heap.push(x));
+        let by_abs: Vec<_> = drain_sorted(&mut heap).iter().map(|x| x.abs()).collect();
+        assert!(by_abs.windows(2).all(|pair| pair[0] >= pair[1]));
+
+        let mut heap = DaryHeap::<_, 2, _>::new_by(|a: &f64, b: &f64| b.total_cmp(a));
+        values.iter().for_each(|&x| heap.push(x as f64 / 3.0));
+        let expected: Vec<_> = ascending.iter().map(|&x| x as f64 / 3.0).collect();
+        assert_eq!(drain_sorted(&mut heap), expected);
+
+        // Sorted order follows the comparator
+        let heap = DaryHeap::<_, 2, MinComparator>::from(values.clone());
+        let mut descending = ascending.clone();
+        descending.reverse();
+        assert_eq!(heap.into_sorted_vec(), descending);
+    }
+
+    // Interleaved push, pop, peek_mut and clear against `BinaryHeap`, with
+    // the invariants checked after every operation
+    fn check_interleaved<const D: usize>() {
+        let mut rng = rand::rng();
+        let mut heap = DaryHeap::<i32, D>::new();
+        let mut std_heap = BinaryHeap::new();
+        for _ in 0..if cfg!(miri) { 300 } else { 20000 } {
+            match rng.random_range(0..100) {
+                0..50 => {
+                    let num = rng.random_range(0..100);
+                    heap.push(num);
+                    std_heap.push(num);
+                }
+                50..90 => assert_eq!(heap.pop(), std_heap.pop()),
+                90..99 => {
+                    let delta = rng.random_range(-100..100);
+                    if let Some(mut top) = heap.peek_mut() {
+                        *top += delta;
+                        *std_heap.peek_mut().unwrap() += delta;
+                    }
+                }
+                _ => {
+                    heap.clear();
+                    std_heap.clear();
+                }
+            }
+            heap.check_invariants();
+            assert_eq!(heap.len(), std_heap.len());
+            assert_eq!(heap.peek(), std_heap.peek());
+        }
+    }
+
+    // Pops every heap size up to a few levels deep, so the last parent ends
+    // up with each possible number of children
+    fn check_every_tail<const D: usize>() {
+        let mut rng = rand::rng();
+        for len in 0..=(D * D + 2 * D + 2) {
+            let values: Vec<i32> = (0..len).map(|_| rng.random_range(0..20)).collect();
+            let mut heap = DaryHeap::<_, D>::new();
+            values.iter().for_each(|&x| heap.push(x));
+            heap.check_invariants();
+            let mut std_heap = BinaryHeap::from(values);
+            while let Some(num) = std_heap.pop() {
+                assert_eq!(heap.pop(), Some(num));
+                heap.check_invariants();
+            }
+            assert_eq!(heap.pop(), None);
+        }
+    }
+
+    #[test]
+    fn test_interleaved_operations() {
+        check_interleaved::<2>();
+        check_interleaved::<3>();
+        check_interleaved::<4>();
+        check_interleaved::<5>();
+        check_interleaved::<7>();
+        check_interleaved::<8>();
+        check_interleaved::<16>();
+        check_interleaved::<17>();
+    }
+
+    #[test]
+    fn test_every_tail() {
+        check_every_tail::<2>();
+        check_every_tail::<3>();
+        check_every_tail::<4>();
+        check_every_tail::<5>();
+        check_every_tail::<6>();
+        check_every_tail::<7>();
+        check_every_tail::<8>();
+        check_every_tail::<9>();
+        check_every_tail::<16>();
+        check_every_tail::<17>();
+    }
+
+    #[test]
+    #[should_panic(expected = "belongs above its parent")]
+    fn test_check_invariants() {
+        let mut heap = DaryHeap::<i32, 3>::from(vec![5, 4, 3, 2, 1]);
+        heap.data[4] = 10;
+        heap.check_invariants();
+    }
 }
@@ -0,0 +1,218 @@
+use crate::union_find::{RollbackUnionFind, UnionFind};
+use fnv::FnvHashMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operation {
+    // Inserts an undirected edge, parallel edges are allowed
+    Add(usize, usize),
+    // Deletes one copy of an edge that is present
+    Remove(usize, usize),
+    // Whether the two vertices are connected at this point
+    Query(usize, usize),
+}
+
+// Answers the queries of `ops` on a graph of n vertices, in order. Each edge
+// is alive during an interval of the timeline, which is stored in the
+// O(log T) nodes of a segment tree over time covering it. A depth-first walk
+// of the tree unions the edges of each node on the way down and rolls them
+// back on the way up, so every leaf sees exactly the edges alive at its time.
+// O(T log T log n) overall.
+pub fn solve(n: usize, ops: &[Operation]) -> Vec<bool> {
+    let time = ops.len();
+    if time == 0 {
+        return Vec::new();
+    }
+
+    // Start times of the copies of each edge that are alive
+    let mut alive: FnvHashMap<(usize, usize), Vec<usize>> = FnvHashMap::default();
+    let mut tree = SegmentTree::new(time);
+    for (t, &op) in ops.iter().enumerate() {
+        match op {
+            Operation::Add(p, q) => {
+                assert!(p < n && q < n, "vertex out of range");
+                alive.entry(edge(p, q)).or_default().push(t);
+            }
+            Operation::Remove(p, q) => {
+                let start = alive
+                    .get_mut(&edge(p, q))
+                    .and_then(Vec::pop)
+                    .expect("removing an edge that is not present");
+                tree.insert(start, t, edge(p, q));
+            }
+            Operation::Query(p, q) => assert!(p < n && q < n, "vertex out of range"),
+        }
+    }
+    for (edge, starts) in alive {
+        starts
+            .into_iter()
+            .for_each(|start| tree.insert(start, time, edge));
+    }
+
+    let mut answers = Vec::new();
+    let mut uf = RollbackUnionFind::new(n);
+    tree.walk(1, 0, time, ops, &mut uf, &mut answers);
+    answers
+}
+
+#[inline]
+fn edge(p: usize, q: usize) -> (usize, usize) {
+    (p.min(q), p.max(q))
+}
+
+struct SegmentTree {
+    time: usize,
+    // Node 1 covers [0, time), the children of node i are 2i and 2i + 1
+    edges: Vec<Vec<(usize, usize)>>,
+}
+
+impl SegmentTree {
+    fn new(time: usize) -> Self {
+        Self {
+            time,
+            edges: vec![Vec::new(); 4 * time],
+        }
+    }
+
+    // Alive during [start, end)
+    fn insert(&mut self, start: usize, end: usize, edge: (usize, usize)) {
+        if start < end {
+            self.insert_rec(1, 0, self.time, start, end, edge);
+        }
+    }
+
+    fn insert_rec(
+        &mut self,
+        node: usize,
+        lo: usize,
+        hi: usize,
+        start: usize,
+        end: usize,
+        edge: (usize, usize),
+    ) {
+        if start <= lo && hi <= end {
+            self.edges[node].push(edge);
+            return;
+        }
+        let mid = lo + (hi - lo) / 2;
+        if start < mid {
+            self.insert_rec(2 * node, lo, mid, start, end, edge);
+        }
+        if mid < end {
+            self.insert_rec(2 * node + 1, mid, hi, start, end, edge);
+        }
+    }
+
+    fn walk(
+        &self,
+        node: usize,
+        lo: usize,
+        hi: usize,
+        ops: &[Operation],
+        uf: &mut RollbackUnionFind,
+        answers: &mut Vec<bool>,
+    ) {
+        let snapshot = uf.snapshot();
+        self.edges[node].iter().for_each(|&(p, q)| {
+            uf.union(p, q);
+        });
+        if hi - lo == 1 {
+            if let Operation::Query(p, q) = ops[lo] {
+                answers.push(uf.connected(p, q));
+            }
+        } else {
+            let mid = lo + (hi - lo) / 2;
+            self.walk(2 * node, lo, mid, ops, uf, answers);
+            self.walk(2 * node + 1, mid, hi, ops, uf, answers);
+        }
+        uf.rollback(snapshot);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::union_find::WeightedQuickUnion;
+    use rand::Rng;
+
+    // Rebuilds the connectivity from the live edges at every query
+    fn brute_force(n: usize, ops: &[Operation]) -> Vec<bool> {
+        let mut edges: Vec<(usize, usize)> = Vec::new();
+        let mut answers = Vec::new();
+        for &op in ops {
+            match op {
+                Operation::Add(p, q) => edges.push(edge(p, q)),
+                Operation::Remove(p, q) => {
+                    let pos = edges.iter().position(|&e| e == edge(p, q)).unwrap();
+                    edges.swap_remove(pos);
+                }
+                Operation::Query(p, q) => {
+                    let mut uf: WeightedQuickUnion = UnionFind::new(n);
+                    edges.iter().for_each(|&(p, q)| {
+                        uf.union(p, q);
+                    });
+                    answers.push(uf.connected(p, q));
+                }
+            }
+        }
+        answers
+    }
+
+    #[test]
+    fn test_small_timeline() {
+        use Operation::*;
+        let ops = [
+            Query(0, 1),
+            Add(0, 1),
+            Add(1, 2),
+            Query(0, 2),
+            Add(2, 0),
+            Remove(1, 0),
+            Query(0, 1),
+            Remove(1, 2),
+            Query(0, 1),
+            Query(2, 0),
+            Add(1, 0),
+            Add(1, 0),
+            Remove(0, 1),
+            Query(1, 0),
+            Query(3, 3),
+        ];
+        assert_eq!(solve(4, &ops), [false, true, true, false, true, true, true]);
+        assert!(solve(4, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_random_timelines() {
+        let mut rng = rand::rng();
+        for _ in 0..20 {
+            let n = rng.random_range(1..12);
+            let mut edges = Vec::new();
+            let ops: Vec<_> = (0..300)
+                .map(|_| match rng.random_range(0..3) {
+                    0 => {
+                        let (p, q) = (rng.random_range(0..n), rng.random_range(0..n));
+                        edges.push((p, q));
+                        Operation::Add(p, q)
+                    }
+                    1 if !edges.is_empty() => {
+                        let (p, q) = edges.swap_remove(rng.random_range(0..edges.len()));
+                        // Either orientation names the same edge
+                        if rng.random_bool(0.5) {
+                            Operation::Remove(p, q)
+                        } else {
+                            Operation::Remove(q, p)
+                        }
+                    }
+                    _ => Operation::Query(rng.random_range(0..n), rng.random_range(0..n)),
+                })
+                .collect();
+            assert_eq!(solve(n, &ops), brute_force(n, &ops));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "not present")]
+    fn test_remove_missing_edge() {
+        solve(3, &[Operation::Add(0, 1), Operation::Remove(1, 2)]);
+    }
+}
@@ -0,0 +1,478 @@
+use crate::compare::{Compare, MaxComparator, MinComparator};
+use std::mem;
+
+const NIL: usize = usize::MAX;
+
+// Refers to an element while it is in the heap. Popping the element, `clear`
+// and melding the heap into another one invalidate it. The generation tells
+// a stale handle from the element that reused its slot: `get` returns `None`
+// and `decrease_key` panics.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Handle(usize, u64);
+
+// Siblings, and the roots, form circular doubly linked lists
+#[derive(Clone, Debug)]
+struct Node<T> {
+    item: Option<T>,
+    // Of the push that filled the slot, starting from 1
+    generation: u64,
+    parent: usize,
+    child: usize,
+    left: usize,
+    right: usize,
+    degree: u32,
+    marked: bool,
+}
+
+// Nodes live in an arena, slots of popped elements are reused by `push`
+#[derive(Clone, Debug)]
+pub struct FibonacciHeap<T, C = MaxComparator> {
+    nodes: Vec<Node<T>>,
+    free: Vec<usize>,
+    // Last generation handed out, kept by `clear`
+    generation: u64,
+    top: usize,
+    len: usize,
+    roots: Vec<usize>,
+    by_degree: Vec<usize>,
+    cmp: C,
+}
+
+impl<T, C: Compare<T> + Default> FibonacciHeap<T, C> {
+    #[inline]
+    pub fn new() -> Self {
+        Self::with_comparator(C::default())
+    }
+
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_comparator(capacity, C::default())
+    }
+}
+
+impl<T: Ord> FibonacciHeap<T, MinComparator> {
+    #[inline]
+    pub fn new_min() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, C: Compare<T>> FibonacciHeap<T, C> {
+    #[inline]
+    pub fn with_comparator(cmp: C) -> Self {
+        Self::with_capacity_and_comparator(0, cmp)
+    }
+
+    #[inline]
+    pub fn with_capacity_and_comparator(capacity: usize, cmp: C) -> Self {
+        FibonacciHeap {
+            nodes: Vec::with_capacity(capacity),
+            free: Vec::new(),
+            generation: 0,
+            top: NIL,
+            len: 0,
+            roots: Vec::new(),
+            by_degree: Vec::new(),
+            cmp,
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    pub fn peek(&self) -> Option<&T> {
+        self.nodes.get(self.top).and_then(|node| node.item.as_ref())
+    }
+
+    #[inline]
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        self.slot(handle).map(|x| self.item(x))
+    }
+
+    // The slot of a handle whose element is still in the heap
+    #[inline]
+    fn slot(&self, handle: Handle) -> Option<usize> {
+        let node = self.nodes.get(handle.0)?;
+        (node.generation == handle.1 && node.item.is_some()).then_some(handle.0)
+    }
+
+    #[inline]
+    pub fn push(&mut self, item: T) -> Handle {
+        self.generation += 1;
+        let x = self.free.pop().unwrap_or(self.nodes.len());
+        let node = Node {
+            item: Some(item),
+            generation: self.generation,
+            parent: NIL,
+            child: NIL,
+            left: x,
+            right: x,
+            degree: 0,
+            marked: false,
+        };
+        if x == self.nodes.len() {
+            self.nodes.push(node);
+        } else {
+            self.nodes[x] = node;
+        }
+        self.add_root(x);
+        self.len += 1;
+        Handle(x, self.generation)
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        if self.top == NIL {
+            return None;
+        }
+        let top = self.top;
+        let item = self.nodes[top].item.take();
+        self.free.push(top);
+        self.len -= 1;
+
+        // The children of the top become roots
+        let child = self.nodes[top].child;
+        if child != NIL {
+            let mut x = child;
+            loop {
+                self.nodes[x].parent = NIL;
+                self.nodes[x].marked = false;
+                x = self.nodes[x].right;
+                if x == child {
+                    break;
+                }
+            }
+            self.splice(top, child);
+        }
+
+        let next = self.nodes[top].right;
+        self.unlink(top);
+        if next == top {
+            self.top = NIL;
+        } else {
+            self.consolidate(next);
+        }
+        item
+    }
+
+    // Replaces the element behind `handle` by one that belongs at least as
+    // high, e.g. a smaller distance in a min-heap
+    pub fn decrease_key(&mut self, handle: Handle, item: T) {
+        let x = self.slot(handle).expect("stale handle");
+        let old = self.item(x);
+        debug_assert!(!self.cmp.gt(old, &item), "the key must not get worse");
+        self.nodes[x].item = Some(item);
+
+        let parent = self.nodes[x].parent;
+        if parent != NIL && self.cmp.gt(self.item(x), self.item(parent)) {
+            self.cut(x);
+            // Cascading cut: a node that lost a second child moves to the roots
+            let mut y = parent;
+            while self.nodes[y].parent != NIL {
+                if !self.nodes[y].marked {
+                    self.nodes[y].marked = true;
+                    break;
+                }
+                let next = self.nodes[y].parent;
+                self.cut(y);
+                y = next;
+            }
+        }
+        if self.cmp.gt(self.item(x), self.item(self.top)) {
+            self.top = x;
+        }
+    }
+
+    // Moves all elements of `other` into `self`, leaving `other` empty. Handles
+    // into `other` are invalidated.
+    pub fn meld(&mut self, other: &mut Self) {
+        let offset = self.nodes.len();
+        let shift = |x: usize| if x == NIL { NIL } else { x + offset };
+        self.nodes.extend(other.nodes.drain(..).map(|node| Node {
+            item: node.item,
+            generation: node.generation,
+            parent: shift(node.parent),
+            child: shift(node.child),
+            left: shift(node.left),
+            right: shift(node.right),
+            degree: node.degree,
+            marked: node.marked,
+        }));
+        self.free.extend(other.free.drain(..).map(shift));
+        self.generation = self.generation.max(other.generation);
+        let other_top = shift(other.top);
+        if other_top != NIL {
+            if self.top == NIL {
+                self.top = other_top;
+            } else {
+                self.splice(self.top, other_top);
+                if self.cmp.gt(self.item(other_top), self.item(self.top)) {
+                    self.top = other_top;
+                }
+            }
+        }
+        self.len += other.len;
+        other.top = NIL;
+        other.len = 0;
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.free.clear();
+        self.top = NIL;
+        self.len = 0;
+    }
+
+    #[inline]
+    fn add_root(&mut self, x: usize) {
+        if self.top == NIL {
+            self.top = x;
+        } else {
+            self.splice(self.top, x);
+            if self.cmp.gt(self.item(x), self.item(self.top)) {
+                self.top = x;
+            }
+        }
+    }
+
+    // Joins the circular lists containing `a` and `b`
+    #[inline]
+    fn splice(&mut self, a: usize, b: usize) {
+        let a_right = self.nodes[a].right;
+        let b_left = self.nodes[b].left;
+        self.nodes[a].right = b;
+        self.nodes[b].left = a;
+        self.nodes[b_left].right = a_right;
+        self.nodes[a_right].left = b_left;
+    }
+
+    // Removes `x` from its list, leaving it as a list of its own
+    #[inline]
+    fn unlink(&mut self, x: usize) {
+        let Node { left, right, .. } = self.nodes[x];
+        self.nodes[left].right = right;
+        self.nodes[right].left = left;
+        self.nodes[x].left = x;
+        self.nodes[x].right = x;
+    }
+
+    // Moves `x` from its parent's children to the roots
+    fn cut(&mut self, x: usize) {
+        let parent = self.nodes[x].parent;
+        if self.nodes[parent].child == x {
+            let right = self.nodes[x].right;
+            self.nodes[parent].child = if right == x { NIL } else { right };
+        }
+        self.nodes[parent].degree -= 1;
+        self.unlink(x);
+        self.nodes[x].parent = NIL;
+        self.nodes[x].marked = false;
+        self.splice(self.top, x);
+    }
+
+    // Links roots of equal degree until all degrees differ, then finds the top
+    fn consolidate(&mut self, start: usize) {
+        let mut roots = mem::take(&mut self.roots);
+        let mut by_degree = mem::take(&mut self.by_degree);
+        let mut x = start;
+        loop {
+            roots.push(x);
+            x = self.nodes[x].right;
+            if x == start {
+                break;
+            }
+        }
+
+        for &root in &roots {
+            let mut x = root;
+            self.unlink(x);
+            loop {
+                let degree = self.nodes[x].degree as usize;
+                if degree >= by_degree.len() {
+                    by_degree.resize(degree + 1, NIL);
+                }
+                let y = by_degree[degree];
+                if y == NIL {
+                    by_degree[degree] = x;
+                    break;
+                }
+                by_degree[degree] = NIL;
+                x = self.link(x, y);
+            }
+        }
+
+        self.top = NIL;
+        for x in by_degree.drain(..).filter(|&x| x != NIL) {
+            self.add_root(x);
+        }
+        roots.clear();
+        self.roots = roots;
+        self.by_degree = by_degree;
+    }
+
+    // Makes the lower of two roots a child of the higher one
+    #[inline]
+    fn link(&mut self, a: usize, b: usize) -> usize {
+        let (top, below) = if self.cmp.gt(self.item(b), self.item(a)) {
+            (b, a)
+        } else {
+            (a, b)
+        };
+        let child = self.nodes[top].child;
+        if child == NIL {
+            self.nodes[top].child = below;
+        } else {
+            self.splice(child, below);
+        }
+        self.nodes[below].parent = top;
+        self.nodes[below].marked = false;
+        self.nodes[top].degree += 1;
+        top
+    }
+
+    #[inline]
+    fn item(&self, x: usize) -> &T {
+        // Every node reachable from the top holds an element
+        self.nodes[x].item.as_ref().unwrap()
+    }
+}
+
+impl<T, C: Compare<T> + Default> Default for FibonacciHeap<T, C> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+    use std::collections::BinaryHeap;
+
+    #[test]
+    fn test_random_operations() {
+        let mut rng = rand::rng();
+        let mut heap = FibonacciHeap::<i32>::new();
+        let mut reference = BinaryHeap::new();
+        for _ in 0..if cfg!(miri) { 1000 } else { 100000 } {
+            if rng.random_bool(0.6) {
+                let num = rng.random_range(-1000..1000);
+                heap.push(num);
+                reference.push(num);
+            } else {
+                assert_eq!(heap.pop(), reference.pop());
+            }
+            assert_eq!(heap.len(), reference.len());
+            assert_eq!(heap.peek(), reference.peek());
+        }
+        while let Some(num) = reference.pop() {
+            assert_eq!(heap.pop(), Some(num));
+        }
+        assert_eq!(heap.pop(), None);
+    }
+
+    #[test]
+    fn test_decrease_key() {
+        let mut rng = rand::rng();
+        let mut heap = FibonacciHeap::<(i32, usize), MinComparator>::new_min();
+        let mut keys = vec![0; 2000];
+        let mut handles = vec![Handle::default(); keys.len()];
+        for (i, key) in keys.iter_mut().enumerate() {
+            *key = rng.random_range(0..100000);
+            handles[i] = heap.push((*key, i));
+        }
+        // Popping a few elements builds trees, so later decreases cut and cascade
+        let mut popped = Vec::new();
+        for round in 0..10 {
+            for _ in 0..500 {
+                let i = rng.random_range(0..keys.len());
+                if popped.iter().any(|&(_, j)| j == i) {
+                    continue;
+                }
+                keys[i] -= rng.random_range(0..1000);
+                heap.decrease_key(handles[i], (keys[i], i));
+                assert_eq!(heap.get(handles[i]), Some(&(keys[i], i)));
+            }
+            if round < 5 {
+                popped.push(heap.pop().unwrap());
+            }
+        }
+
+        let mut expected: Vec<_> = keys.iter().enumerate().map(|(i, &k)| (k, i)).collect();
+        expected.retain(|entry| !popped.contains(entry));
+        expected.sort_unstable();
+        let rest: Vec<_> = std::iter::from_fn(|| heap.pop()).collect();
+        assert_eq!(rest, expected);
+    }
+
+    #[test]
+    fn test_stale_handle() {
+        let mut heap = FibonacciHeap::<i32, MinComparator>::new_min();
+        let old = heap.push(5);
+        heap.push(7);
+        assert_eq!(heap.pop(), Some(5));
+        // Reuses the slot of 5
+        let new = heap.push(9);
+        assert_eq!(heap.get(old), None);
+        assert_eq!(heap.get(new), Some(&9));
+        heap.clear();
+        let cleared = heap.push(1);
+        assert_eq!(heap.get(new), None);
+        assert_eq!(heap.get(cleared), Some(&1));
+        assert_eq!(heap.get(Handle::default()), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "stale handle")]
+    fn test_decrease_stale_handle() {
+        let mut heap = FibonacciHeap::<i32, MinComparator>::new_min();
+        let old = heap.push(5);
+        heap.pop();
+        heap.push(9);
+        heap.decrease_key(old, 1);
+    }
+
+    #[test]
+    fn test_meld() {
+        let mut rng = rand::rng();
+        let mut a = FibonacciHeap::<i32>::new();
+        let mut b = FibonacciHeap::<i32>::new();
+        let mut values = Vec::new();
+        for _ in 0..500 {
+            let num = rng.random_range(0..1000);
+            values.push(num);
+            if rng.random_bool(0.5) {
+                a.push(num);
+            } else {
+                b.push(num);
+            }
+        }
+        // Leave free slots and consolidated trees in both arenas
+        for heap in [&mut a, &mut b] {
+            for _ in 0..10 {
+                values.remove(values.iter().position(|x| Some(x) == heap.peek()).unwrap());
+                heap.pop();
+            }
+        }
+
+        a.meld(&mut b);
+        assert!(b.is_empty() && b.pop().is_none());
+        assert_eq!(a.len(), values.len());
+        values.extend((0..50).map(|i| i * 7));
+        (0..50).for_each(|i| {
+            a.push(i * 7);
+        });
+        values.sort_unstable_by(|x, y| y.cmp(x));
+        let popped: Vec<_> = std::iter::from_fn(|| a.pop()).collect();
+        assert_eq!(popped, values);
+    }
+}
@@ -0,0 +1,378 @@
+use crate::graph::{DijkstraQueue, Graph};
+use std::collections::VecDeque;
+
+const FLOATING_POINT_EPSILON: f64 = 1e-11;
+
+// Edges are stored in pairs: `e` is the forward edge and `e ^ 1` its residual
+// twin, which has zero capacity and carries the negated flow.
+#[derive(Clone)]
+struct FlowEdge {
+    to: usize,
+    capacity: f64,
+    flow: f64,
+}
+
+impl FlowEdge {
+    #[inline]
+    fn residual(&self) -> f64 {
+        self.capacity - self.flow
+    }
+}
+
+pub struct FlowNetwork {
+    adj: Vec<Vec<usize>>,
+    edges: Vec<FlowEdge>,
+}
+
+pub struct MinCut {
+    pub source_side: Vec<bool>,
+    pub edges: Vec<(usize, usize, f64)>,
+    pub capacity: f64,
+}
+
+impl FlowNetwork {
+    #[inline]
+    pub fn new(size: usize) -> Self {
+        FlowNetwork {
+            adj: vec![vec![]; size],
+            edges: Vec::new(),
+        }
+    }
+
+    // `capacity` maps each directed edge (from, to, weight) of the graph to its capacity
+    pub fn from_graph<
+        F: Fn(usize, usize, f64) -> f64,
+        const CALCPATH: bool,
+        const HEURISTIC: bool,
+        const EARLYSTOP: bool,
+        Q: DijkstraQueue,
+    >(
+        graph: &Graph<CALCPATH, HEURISTIC, EARLYSTOP, Q>,
+        capacity: F,
+    ) -> Self {
+        let mut network = FlowNetwork::new(graph.num_nodes());
+        graph.edges().for_each(|(u, v, weight)| {
+            network.add_edge(u, v, capacity(u, v, weight));
+        });
+        network
+    }
+
+    // Returns the id of the new edge, usable with `flow`
+    #[inline]
+    pub fn add_edge(&mut self, from: usize, to: usize, capacity: f64) -> usize {
+        assert!(capacity >= 0.0, "capacity must be non-negative");
+        assert!(capacity.is_finite(), "capacity must be finite");
+        let id = self.edges.len();
+        self.edges.push(FlowEdge {
+            to,
+            capacity,
+            flow: 0.0,
+        });
+        self.edges.push(FlowEdge {
+            to: from,
+            capacity: 0.0,
+            flow: 0.0,
+        });
+        self.adj[from].push(id);
+        self.adj[to].push(id + 1);
+        id
+    }
+
+    #[inline]
+    pub fn num_nodes(&self) -> usize {
+        self.adj.len()
+    }
+
+    #[inline]
+    pub fn num_edges(&self) -> usize {
+        self.edges.len() / 2
+    }
+
+    #[inline]
+    pub fn flow(&self, edge: usize) -> f64 {
+        self.edges[edge].flow
+    }
+
+    // (from, to, capacity, flow) for every edge added by `add_edge`
+    #[inline]
+    pub fn edges(&self) -> impl Iterator<Item = (usize, usize, f64, f64)> + '_ {
+        self.edges
+            .chunks_exact(2)
+            .map(|pair| (pair[1].to, pair[0].to, pair[0].capacity, pair[0].flow))
+    }
+
+    #[inline]
+    pub fn reset(&mut self) {
+        self.edges.iter_mut().for_each(|e| e.flow = 0.0);
+    }
+
+    pub fn edmonds_karp(&mut self, s: usize, t: usize) -> f64 {
+        assert!(s != t, "source and sink must differ");
+        self.reset();
+        let n = self.num_nodes();
+        let mut edge_to = vec![usize::MAX; n];
+        let mut queue = VecDeque::with_capacity(n);
+        let mut value = 0.0;
+
+        loop {
+            edge_to.fill(usize::MAX);
+            queue.clear();
+            queue.push_back(s);
+            while let Some(u) = queue.pop_front() {
+                for &e in &self.adj[u] {
+                    let v = self.edges[e].to;
+                    if v != s
+                        && edge_to[v] == usize::MAX
+                        && self.edges[e].residual() > FLOATING_POINT_EPSILON
+                    {
+                        edge_to[v] = e;
+                        if v == t {
+                            break;
+                        }
+                        queue.push_back(v);
+                    }
+                }
+                if edge_to[t] != usize::MAX {
+                    break;
+                }
+            }
+            if edge_to[t] == usize::MAX {
+                return value;
+            }
+
+            let mut bottleneck = f64::INFINITY;
+            let mut v = t;
+            while v != s {
+                let e = edge_to[v];
+                bottleneck = bottleneck.min(self.edges[e].residual());
+                v = self.edges[e ^ 1].to;
+            }
+            let mut v = t;
+            while v != s {
+                let e = edge_to[v];
+                self.augment(e, bottleneck);
+                v = self.edges[e ^ 1].to;
+            }
+            value += bottleneck;
+        }
+    }
+
+    pub fn dinic(&mut self, s: usize, t: usize) -> f64 {
+        assert!(s != t, "source and sink must differ");
+        self.reset();
+        let n = self.num_nodes();
+        let mut level = vec![usize::MAX; n];
+        let mut next = vec![0; n];
+        let mut queue = VecDeque::with_capacity(n);
+        let mut path: Vec<usize> = Vec::new();
+        let mut value = 0.0;
+
+        loop {
+            level.fill(usize::MAX);
+            level[s] = 0;
+            queue.clear();
+            queue.push_back(s);
+            while let Some(u) = queue.pop_front() {
+                for &e in &self.adj[u] {
+                    let v = self.edges[e].to;
+                    if level[v] == usize::MAX && self.edges[e].residual() > FLOATING_POINT_EPSILON {
+                        level[v] = level[u] + 1;
+                        queue.push_back(v);
+                    }
+                }
+            }
+            if level[t] == usize::MAX {
+                return value;
+            }
+
+            // Iterative blocking-flow search, road networks are too deep for recursion
+            next.fill(0);
+            path.clear();
+            let mut u = s;
+            loop {
+                if u == t {
+                    let bottleneck = path
+                        .iter()
+                        .map(|&e| self.edges[e].residual())
+                        .fold(f64::INFINITY, f64::min);
+                    path.iter().for_each(|&e| self.augment(e, bottleneck));
+                    value += bottleneck;
+                    // Retreat to the tail of the first saturated edge
+                    let saturated = path
+                        .iter()
+                        .position(|&e| self.edges[e].residual() <= FLOATING_POINT_EPSILON)
+                        .unwrap_or(0);
+                    path.truncate(saturated);
+                    u = path.last().map_or(s, |&e| self.edges[e].to);
+                    continue;
+                }
+
+                let mut advanced = false;
+                while next[u] < self.adj[u].len() {
+                    let e = self.adj[u][next[u]];
+                    let v = self.edges[e].to;
+                    if level[v] == level[u] + 1 && self.edges[e].residual() > FLOATING_POINT_EPSILON
+                    {
+                        path.push(e);
+                        u = v;
+                        advanced = true;
+                        break;
+                    }
+                    next[u] += 1;
+                }
+                if advanced {
+                    continue;
+                }
+
+                // Dead end: prune `u` from the level graph and back up one edge
+                level[u] = usize::MAX;
+                match path.pop() {
+                    Some(e) => {
+                        u = self.edges[e ^ 1].to;
+                        next[u] += 1;
+                    }
+                    None => break,
+                }
+            }
+        }
+    }
+
+    // Call after a max-flow computation from `s`
+    pub fn min_cut(&self, s: usize) -> MinCut {
+        let n = self.num_nodes();
+        let mut source_side = vec![false; n];
+        let mut queue = VecDeque::with_capacity(n);
+        source_side[s] = true;
+        queue.push_back(s);
+        while let Some(u) = queue.pop_front() {
+            for &e in &self.adj[u] {
+                let v = self.edges[e].to;
+                if !source_side[v] && self.edges[e].residual() > FLOATING_POINT_EPSILON {
+                    source_side[v] = true;
+                    queue.push_back(v);
+                }
+            }
+        }
+
+        let edges: Vec<_> = self
+            .edges()
+            .filter(|&(u, v, _, _)| source_side[u] && !source_side[v])
+            .map(|(u, v, capacity, _)| (u, v, capacity))
+            .collect();
+        let capacity = edges.iter().map(|&(_, _, capacity)| capacity).sum();
+        MinCut {
+            source_side,
+            edges,
+            capacity,
+        }
+    }
+
+    #[inline]
+    fn augment(&mut self, e: usize, delta: f64) {
+        self.edges[e].flow += delta;
+        self.edges[e ^ 1].flow -= delta;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::PairingQueue;
+    use rand::Rng;
+
+    const EPSILON: f64 = 1e-9;
+
+    fn check_flow(network: &FlowNetwork, s: usize, t: usize, value: f64) {
+        let mut excess = vec![0.0; network.num_nodes()];
+        for (u, v, capacity, flow) in network.edges() {
+            assert!(flow >= -EPSILON && flow <= capacity + EPSILON);
+            excess[u] -= flow;
+            excess[v] += flow;
+        }
+        for (v, &x) in excess.iter().enumerate() {
+            if v == s {
+                assert!((x + value).abs() < EPSILON);
+            } else if v == t {
+                assert!((x - value).abs() < EPSILON);
+            } else {
+                assert!(x.abs() < EPSILON);
+            }
+        }
+    }
+
+    #[test]
+    fn test_small_network() {
+        // CLRS figure 26.1
+        let mut network = FlowNetwork::new(6);
+        for &(u, v, c) in &[
+            (0, 1, 16.0),
+            (0, 2, 13.0),
+            (1, 3, 12.0),
+            (2, 1, 4.0),
+            (2, 4, 14.0),
+            (3, 2, 9.0),
+            (3, 5, 20.0),
+            (4, 3, 7.0),
+            (4, 5, 4.0),
+        ] {
+            network.add_edge(u, v, c);
+        }
+        assert_eq!(network.edmonds_karp(0, 5), 23.0);
+        assert_eq!(network.min_cut(0).capacity, 23.0);
+        assert_eq!(network.dinic(0, 5), 23.0);
+        let cut = network.min_cut(0);
+        assert_eq!(cut.capacity, 23.0);
+        assert!(cut.source_side[0] && !cut.source_side[5]);
+    }
+
+    #[test]
+    fn test_max_flow_equals_min_cut() {
+        let mut rng = rand::rng();
+        for _ in 0..200 {
+            let n = rng.random_range(2..30);
+            let m = rng.random_range(0..n * 4);
+            let mut network = FlowNetwork::new(n);
+            for _ in 0..m {
+                let u = rng.random_range(0..n);
+                let v = rng.random_range(0..n);
+                network.add_edge(u, v, rng.random_range(0..100) as f64 / 4.0);
+            }
+
+            let ek = network.edmonds_karp(0, n - 1);
+            check_flow(&network, 0, n - 1, ek);
+            assert!((network.min_cut(0).capacity - ek).abs() < EPSILON);
+
+            let dinic = network.dinic(0, n - 1);
+            check_flow(&network, 0, n - 1, dinic);
+            assert!((network.min_cut(0).capacity - dinic).abs() < EPSILON);
+            assert!((ek - dinic).abs() < EPSILON);
+        }
+    }
+
+    #[test]
+    fn test_from_graph() {
+        // Any queue the graph was built for will do
+        let coord = [(0, 0), (3, 4), (6, 0), (3, -4)];
+        let mut graph = Graph::<false, false, false, PairingQueue>::new(coord.len(), &coord);
+        for &(u, v) in &[(0, 1), (1, 2), (0, 3), (3, 2), (1, 3)] {
+            graph.add_edge(u, v);
+            graph.add_edge(v, u);
+        }
+
+        // Unit capacities: two edge-disjoint paths from 0 to 2
+        let mut network = FlowNetwork::from_graph(&graph, |_, _, _| 1.0);
+        assert_eq!(network.num_edges(), 10);
+        assert_eq!(network.dinic(0, 2), 2.0);
+        assert_eq!(network.min_cut(0).edges.len(), 2);
+
+        // Capacities equal to the edge lengths
+        let mut network = FlowNetwork::from_graph(&graph, |_, _, weight| weight);
+        assert!((network.edmonds_karp(0, 2) - 10.0).abs() < EPSILON);
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity must be finite")]
+    fn test_infinite_capacity() {
+        FlowNetwork::new(2).add_edge(0, 1, f64::INFINITY);
+    }
+}
@@ -1,8 +1,128 @@
+use crate::compare::Compare;
 use crate::dary_heap::DaryHeap;
-use ordered_float::NotNan;
-use std::cmp::Reverse;
+use crate::fibonacci_heap::{self, FibonacciHeap};
+use crate::pairing_heap::{self, PairingHeap};
+use std::cmp::Ordering;
 use std::collections::BinaryHeap;
-type DistWrapper = NotNan<f64>;
+
+// Min-heap on the distance, ties broken by the larger node index. `gt` is
+// left to `compare`, so both agree on -0.0 and NaN.
+#[derive(Clone, Copy, Default)]
+pub struct DistComparator;
+
+impl Compare<(f64, usize)> for DistComparator {
+    #[inline]
+    fn compare(&self, a: &(f64, usize), b: &(f64, usize)) -> Ordering {
+        b.0.total_cmp(&a.0).then(a.1.cmp(&b.1))
+    }
+}
+
+pub type DaryQueue = DaryHeap<(f64, usize), 2, DistComparator>;
+pub type PairingQueue = PairingHeap<(f64, usize), DistComparator>;
+pub type FibonacciQueue = FibonacciHeap<(f64, usize), DistComparator>;
+
+// The priority queue used by `Graph::dijkstra`. Queues without decrease-key
+// get a new entry per relaxation and skip stale ones when popping.
+pub trait DijkstraQueue {
+    type Handle: Copy + Default;
+    const DECREASE_KEY: bool;
+
+    fn with_capacity(capacity: usize) -> Self;
+    fn clear(&mut self);
+    fn push(&mut self, dist: f64, u: usize) -> Self::Handle;
+    fn decrease_key(&mut self, handle: Self::Handle, dist: f64, u: usize);
+    fn pop(&mut self) -> Option<(f64, usize)>;
+}
+
+impl DijkstraQueue for DaryQueue {
+    type Handle = ();
+    const DECREASE_KEY: bool = false;
+
+    #[inline]
+    fn with_capacity(capacity: usize) -> Self {
+        DaryHeap::with_capacity(capacity)
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        DaryHeap::clear(self);
+    }
+
+    #[inline]
+    fn push(&mut self, dist: f64, u: usize) {
+        DaryHeap::push(self, (dist, u));
+    }
+
+    #[inline]
+    fn decrease_key(&mut self, _: (), _: f64, _: usize) {
+        unreachable!("DaryHeap has no decrease-key");
+    }
+
+    #[inline]
+    fn pop(&mut self) -> Option<(f64, usize)> {
+        DaryHeap::pop(self)
+    }
+}
+
+impl DijkstraQueue for PairingQueue {
+    type Handle = pairing_heap::Handle;
+    const DECREASE_KEY: bool = true;
+
+    #[inline]
+    fn with_capacity(capacity: usize) -> Self {
+        PairingHeap::with_capacity(capacity)
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        PairingHeap::clear(self);
+    }
+
+    #[inline]
+    fn push(&mut self, dist: f64, u: usize) -> Self::Handle {
+        PairingHeap::push(self, (dist, u))
+    }
+
+    #[inline]
+    fn decrease_key(&mut self, handle: Self::Handle, dist: f64, u: usize) {
+        PairingHeap::decrease_key(self, handle, (dist, u));
+    }
+
+    #[inline]
+    fn pop(&mut self) -> Option<(f64, usize)> {
+        PairingHeap::pop(self)
+    }
+}
+
+impl DijkstraQueue for FibonacciQueue {
+    type Handle = fibonacci_heap::Handle;
+    const DECREASE_KEY: bool = true;
+
+    #[inline]
+    fn with_capacity(capacity: usize) -> Self {
+        FibonacciHeap::with_capacity(capacity)
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        FibonacciHeap::clear(self);
+    }
+
+    #[inline]
+    fn push(&mut self, dist: f64, u: usize) -> Self::Handle {
+        FibonacciHeap::push(self, (dist, u))
+    }
+
+    #[inline]
+    fn decrease_key(&mut self, handle: Self::Handle, dist: f64, u: usize) {
+        FibonacciHeap::decrease_key(self, handle, (dist, u));
+    }
+
+    #[inline]
+    fn pop(&mut self) -> Option<(f64, usize)> {
+        FibonacciHeap::pop(self)
+    }
+}
 
 #[derive(Clone)]
 struct Edge {
@@ -10,7 +130,12 @@ struct Edge {
     weight: f64,
 }
 
-pub struct Graph<const CALCPATH: bool, const HEURISTIC: bool, const EARLYSTOP: bool> {
+pub struct Graph<
+    const CALCPATH: bool,
+    const HEURISTIC: bool,
+    const EARLYSTOP: bool,
+    Q: DijkstraQueue = DaryQueue,
+> {
     edges: Vec<Vec<Edge>>,
     coord: Vec<(i32, i32)>,
     distances: Vec<f64>,
@@ -18,13 +143,15 @@ pub struct Graph<const CALCPATH: bool, const HEURISTIC: bool, const EARLYSTOP: b
     visited: Vec<bool>,
     calced: Vec<bool>,
     // heap: BinaryHeap<(Reverse<DistWrapper>, usize)>,
-    heap: DaryHeap<(Reverse<DistWrapper>, usize), 2>,
+    heap: Q,
+    // Queue entry of every node in the heap, only kept if Q::DECREASE_KEY
+    handles: Vec<Q::Handle>,
     path: Vec<usize>,
     num_edges: usize,
 }
 
-impl<const CALCPATH: bool, const HEURISTIC: bool, const EARLYSTOP: bool>
-    Graph<CALCPATH, HEURISTIC, EARLYSTOP>
+impl<const CALCPATH: bool, const HEURISTIC: bool, const EARLYSTOP: bool, Q: DijkstraQueue>
+    Graph<CALCPATH, HEURISTIC, EARLYSTOP, Q>
 {
     #[inline]
     pub fn new(size: usize, coord: &[(i32, i32)]) -> Self {
@@ -32,15 +159,27 @@ impl<const CALCPATH: bool, const HEURISTIC: bool, const EARLYSTOP: bool>
             "HEURISTIC: {}, EARLYSTOP: {}, CALCPATH: {}",
             HEURISTIC, EARLYSTOP, CALCPATH
         );
+        Self::with_coords(size, coord.to_vec())
+    }
+
+    // For graphs whose weights come from `add_weighted_edge` rather than coordinates
+    #[inline]
+    pub fn without_coords(size: usize) -> Self {
+        Self::with_coords(size, vec![(0, 0); size])
+    }
+
+    #[inline]
+    fn with_coords(size: usize, coord: Vec<(i32, i32)>) -> Self {
         Graph {
             edges: vec![vec![]; size],
-            coord: coord.to_vec(),
+            coord,
             distances: vec![f64::INFINITY; size],
             changed: Vec::with_capacity(size),
             visited: vec![false; size],
             calced: vec![false; size],
             // heap: BinaryHeap::with_capacity(2 * size),
-            heap: DaryHeap::with_capacity(2 * size),
+            heap: Q::with_capacity(if Q::DECREASE_KEY { size } else { 2 * size }),
+            handles: vec![Q::Handle::default(); if Q::DECREASE_KEY { size } else { 0 }],
             path: vec![0; size],
             num_edges: 0,
         }
@@ -55,6 +194,12 @@ impl<const CALCPATH: bool, const HEURISTIC: bool, const EARLYSTOP: bool>
         });
     }
 
+    #[inline]
+    pub fn add_weighted_edge(&mut self, from: usize, to: usize, weight: f64) {
+        self.num_edges += 1;
+        self.edges[from].push(Edge { to, weight });
+    }
+
     #[inline]
     pub fn dijkstra(&mut self, start: usize, end: usize) -> f64 {
         if EARLYSTOP {
@@ -86,14 +231,14 @@ impl<const CALCPATH: bool, const HEURISTIC: bool, const EARLYSTOP: bool>
         }
 
         self.heap.clear();
-        self.heap.push((
-            Reverse(DistWrapper::new(*self.get_dist(start)).unwrap()),
-            start,
-        ));
-
-        while let Some((dist_wrapper, u)) = self.heap.pop() {
-            let dist: f64 = dist_wrapper.0.into();
+        let handle = self.heap.push(*self.get_dist(start), start);
+        if Q::DECREASE_KEY {
+            unsafe {
+                *self.handles.get_unchecked_mut(start) = handle;
+            }
+        }
 
+        while let Some((dist, u)) = self.heap.pop() {
             if *self.get_calced(u) {
                 continue;
             }
@@ -121,12 +266,23 @@ impl<const CALCPATH: bool, const HEURISTIC: bool, const EARLYSTOP: bool>
                     next_dist = dist + weight;
                 }
 
-                if next_dist < *self.get_dist(v) {
+                let old_dist = *self.get_dist(v);
+                if next_dist < old_dist {
                     unsafe {
                         *self.distances.get_unchecked_mut(v) = next_dist;
                     }
-                    self.heap
-                        .push((Reverse(DistWrapper::new(next_dist).unwrap()), v));
+                    // A reached but unsettled node is still in the heap
+                    if Q::DECREASE_KEY && old_dist < f64::INFINITY {
+                        let handle = unsafe { *self.handles.get_unchecked(v) };
+                        self.heap.decrease_key(handle, next_dist, v);
+                    } else {
+                        let handle = self.heap.push(next_dist, v);
+                        if Q::DECREASE_KEY {
+                            unsafe {
+                                *self.handles.get_unchecked_mut(v) = handle;
+                            }
+                        }
+                    }
 
                     if CALCPATH {
                         unsafe {
@@ -158,6 +314,61 @@ impl<const CALCPATH: bool, const HEURISTIC: bool, const EARLYSTOP: bool>
         (u.0 - v.0).abs().max((u.1 - v.1).abs()) as f64
     }
 
+    #[inline]
+    pub fn num_nodes(&self) -> usize {
+        self.edges.len()
+    }
+
+    #[inline]
+    pub fn num_edges(&self) -> usize {
+        self.num_edges
+    }
+
+    #[inline]
+    pub fn coords(&self) -> &[(i32, i32)] {
+        &self.coord
+    }
+
+    // (to, weight) for every edge leaving `u`
+    #[inline]
+    pub fn adj(&self, u: usize) -> impl Iterator<Item = (usize, f64)> + '_ {
+        self.edges[u]
+            .iter()
+            .map(|&Edge { to, weight }| (to, weight))
+    }
+
+    // (from, to, weight) for every directed edge
+    #[inline]
+    pub fn edges(&self) -> impl Iterator<Item = (usize, usize, f64)> + '_ {
+        self.edges.iter().enumerate().flat_map(|(u, edges)| {
+            edges
+                .iter()
+                .map(move |&Edge { to, weight }| (u, to, weight))
+        })
+    }
+
+    // Nodes settled (popped from the heap) by the last call to `dijkstra`
+    #[inline]
+    pub fn settled(&self) -> impl Iterator<Item = usize> + '_ {
+        self.calced
+            .iter()
+            .enumerate()
+            .filter(|(_, calced)| **calced)
+            .map(|(u, _)| u)
+    }
+
+    // Distance found by the last call to `dijkstra`
+    #[inline]
+    pub fn dist_to(&self, u: usize) -> f64 {
+        self.distances[u]
+    }
+
+    // Predecessor of `u` on the last shortest path tree, only meaningful if `u` was reached
+    #[inline]
+    pub fn parent(&self, u: usize) -> usize {
+        self.path[u]
+    }
+
     #[inline]
     pub fn get_path(&self, start: usize, end: usize) -> (usize, impl Iterator<Item = usize>) {
         let mut path = Vec::new();
@@ -225,3 +436,24 @@ impl<const CALCPATH: bool, const HEURISTIC: bool, const EARLYSTOP: bool>
         unsafe { self.edges.get_unchecked(u).get_unchecked(v) }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dist_comparator() {
+        let values = [f64::NAN, -f64::NAN, f64::INFINITY, 1.0, 0.0, -0.0, -1.0];
+        let cmp = DistComparator;
+        for &x in &values {
+            for &y in &values {
+                for (a, b) in [((x, 0), (y, 0)), ((x, 0), (y, 1))] {
+                    assert_eq!(cmp.gt(&a, &b), cmp.compare(&a, &b) == Ordering::Greater);
+                }
+            }
+        }
+        assert!(cmp.gt(&(-0.0, 0), &(0.0, 0)));
+        assert!(cmp.gt(&(1.0, 0), &(f64::NAN, 0)));
+        assert!(cmp.gt(&(1.0, 1), &(1.0, 0)));
+    }
+}
@@ -1,9 +1,20 @@
 pub mod aho_corasick_bitmap;
 pub mod aho_corasick_fixed_vector;
 pub mod aho_corasick_hashmap;
+pub mod aligned_dary_heap;
+pub mod all_pairs;
+pub mod compare;
+pub mod dag;
 pub mod dary_heap;
+pub mod dynamic_connectivity;
+pub mod fibonacci_heap;
+pub mod flow_network;
 pub mod graph;
+pub mod min_max_heap;
+pub mod pairing_heap;
 pub mod percolation;
+pub mod route_render;
 pub mod sort;
+pub mod top_k;
 pub mod union_find;
 pub mod utils;
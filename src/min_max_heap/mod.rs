@@ -0,0 +1,296 @@
+use crate::dary_heap::Hole;
+use likely_stable::unlikely;
+use std::{ptr, slice};
+
+// Binary tree whose even levels (the root's included) are min levels and odd
+// levels max levels: every node is the smallest, respectively largest, element
+// of its subtree.
+#[derive(Clone, Debug)]
+pub struct MinMaxHeap<T> {
+    data: Vec<T>,
+}
+
+impl<T: Ord> MinMaxHeap<T> {
+    #[inline]
+    pub fn new() -> Self {
+        MinMaxHeap { data: Vec::new() }
+    }
+
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        MinMaxHeap {
+            data: Vec::with_capacity(capacity),
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    // Arbitrary order
+    #[inline]
+    pub fn iter(&self) -> slice::Iter<'_, T> {
+        self.data.iter()
+    }
+
+    #[inline]
+    pub fn peek_min(&self) -> Option<&T> {
+        self.data.first()
+    }
+
+    #[inline]
+    pub fn peek_max(&self) -> Option<&T> {
+        self.max_index()
+            .map(|pos| unsafe { self.data.get_unchecked(pos) })
+    }
+
+    #[inline]
+    pub fn push(&mut self, item: T) {
+        let pos = self.data.len();
+        self.data.push(item);
+        // SAFETY: pos < self.data.len()
+        unsafe {
+            self.bubble_up(pos);
+        }
+    }
+
+    #[inline]
+    pub fn pop_min(&mut self) -> Option<T> {
+        let item = self.data.pop()?;
+        if unlikely(self.data.is_empty()) {
+            return Some(item);
+        }
+        // SAFETY: the heap is not empty, and the moved-out root is refilled
+        // before anything can panic
+        unsafe {
+            let min = ptr::read(self.data.as_ptr());
+            self.trickle_down::<false>(0, item);
+            Some(min)
+        }
+    }
+
+    #[inline]
+    pub fn pop_max(&mut self) -> Option<T> {
+        let pos = self.max_index()?;
+        let item = self.data.pop()?;
+        if pos == self.data.len() {
+            return Some(item);
+        }
+        // SAFETY: pos < self.data.len(), and the moved-out slot is refilled
+        // before anything can panic
+        unsafe {
+            let max = ptr::read(self.data.as_ptr().add(pos));
+            self.trickle_down::<true>(pos, item);
+            Some(max)
+        }
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.data.clear();
+    }
+
+    #[inline]
+    pub fn into_vec(self) -> Vec<T> {
+        self.data
+    }
+
+    // The largest element is the root or one of its children
+    #[inline]
+    fn max_index(&self) -> Option<usize> {
+        match self.data.len() {
+            0 => None,
+            1 => Some(0),
+            2 => Some(1),
+            _ => Some(1 + (self.data[2] > self.data[1]) as usize),
+        }
+    }
+
+    // SAFETY: pos < self.data.len()
+    #[inline]
+    unsafe fn bubble_up(&mut self, pos: usize) {
+        if pos == 0 {
+            return;
+        }
+        let mut hole = unsafe { Hole::new(&mut self.data, pos) };
+        let parent = (pos - 1) / 2;
+        // An element on a min level may belong to the max levels above it, and
+        // vice versa
+        if is_min_level(pos) {
+            if hole.element() > unsafe { hole.get(parent) } {
+                unsafe { hole.move_to(parent) };
+                Self::bubble_up_grandparents::<true>(&mut hole);
+            } else {
+                Self::bubble_up_grandparents::<false>(&mut hole);
+            }
+        } else if hole.element() < unsafe { hole.get(parent) } {
+            unsafe { hole.move_to(parent) };
+            Self::bubble_up_grandparents::<false>(&mut hole);
+        } else {
+            Self::bubble_up_grandparents::<true>(&mut hole);
+        }
+    }
+
+    // Sifts up along the levels of the hole's own kind
+    #[inline]
+    fn bubble_up_grandparents<const MAX: bool>(hole: &mut Hole<'_, T>) {
+        while hole.pos() >= 3 {
+            let grandparent = (hole.pos() - 3) / 4;
+            if !better::<MAX, T>(hole.element(), unsafe { hole.get(grandparent) }) {
+                break;
+            }
+            unsafe { hole.move_to(grandparent) };
+        }
+    }
+
+    // Places `item` at `pos`, on a max level if MAX and a min level otherwise
+    // SAFETY: pos < self.data.len() and the element at pos has been moved out
+    #[inline]
+    unsafe fn trickle_down<const MAX: bool>(&mut self, pos: usize, item: T) {
+        let len = self.data.len();
+        let mut hole = unsafe { Hole::with_element(&mut self.data, pos, item) };
+        loop {
+            let pos = hole.pos();
+            let child = 2 * pos + 1;
+            if child >= len {
+                break;
+            }
+
+            // Best among the children and grandchildren
+            let mut best = child;
+            if child + 1 < len
+                && better::<MAX, T>(unsafe { hole.get(child + 1) }, unsafe { hole.get(best) })
+            {
+                best = child + 1;
+            }
+            let grandchild = 4 * pos + 3;
+            for i in grandchild..(grandchild + 4).min(len) {
+                if better::<MAX, T>(unsafe { hole.get(i) }, unsafe { hole.get(best) }) {
+                    best = i;
+                }
+            }
+
+            if !better::<MAX, T>(unsafe { hole.get(best) }, hole.element()) {
+                break;
+            }
+            unsafe { hole.move_to(best) };
+            if best < grandchild {
+                break;
+            }
+            // The parent sits on a level of the other kind
+            let parent = (best - 1) / 2;
+            if better::<MAX, T>(unsafe { hole.get(parent) }, hole.element()) {
+                unsafe { hole.swap_with(parent) };
+            }
+        }
+    }
+}
+
+impl<T: Ord> Default for MinMaxHeap<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[inline]
+fn is_min_level(pos: usize) -> bool {
+    (pos + 1).ilog2().is_multiple_of(2)
+}
+
+// `a` belongs closer to the top of a max (MAX) or min level than `b`
+#[inline]
+fn better<const MAX: bool, T: Ord>(a: &T, b: &T) -> bool {
+    if MAX { a > b } else { a < b }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    fn check_levels(heap: &MinMaxHeap<i32>) {
+        let data = &heap.data;
+        for pos in 1..data.len() {
+            let mut ancestor = (pos - 1) / 2;
+            loop {
+                if is_min_level(ancestor) {
+                    assert!(data[ancestor] <= data[pos]);
+                } else {
+                    assert!(data[ancestor] >= data[pos]);
+                }
+                if ancestor == 0 {
+                    break;
+                }
+                ancestor = (ancestor - 1) / 2;
+            }
+        }
+    }
+
+    #[test]
+    fn test_random_operations() {
+        let mut rng = rand::rng();
+        let mut heap = MinMaxHeap::new();
+        // Sorted reference
+        let mut vec: Vec<i32> = Vec::new();
+
+        (0..if cfg!(miri) { 1000 } else { 200000 }).for_each(|_| match rng.random_range(0..4) {
+            0 | 1 => {
+                let num = rng.random_range(-1000..1000);
+                heap.push(num);
+                let pos = vec.partition_point(|&x| x < num);
+                vec.insert(pos, num);
+            }
+            2 => {
+                assert_eq!(heap.pop_min(), (!vec.is_empty()).then(|| vec.remove(0)));
+            }
+            _ => {
+                assert_eq!(heap.pop_max(), vec.pop());
+            }
+        });
+        assert_eq!(heap.len(), vec.len());
+        assert_eq!(heap.peek_min(), vec.first());
+        assert_eq!(heap.peek_max(), vec.last());
+        check_levels(&heap);
+
+        while !vec.is_empty() {
+            if rng.random_bool(0.5) {
+                assert_eq!(heap.pop_min(), Some(vec.remove(0)));
+            } else {
+                assert_eq!(heap.pop_max(), vec.pop());
+            }
+        }
+        assert_eq!(heap.pop_min(), None);
+        assert_eq!(heap.pop_max(), None);
+    }
+
+    #[test]
+    fn test_small_heaps() {
+        for len in 0..40 {
+            let values: Vec<i32> = (0..len).map(|i| (i * 17) % 23).collect();
+            let mut sorted = values.clone();
+            sorted.sort_unstable();
+
+            let mut heap = MinMaxHeap::new();
+            values.iter().for_each(|&x| heap.push(x));
+            check_levels(&heap);
+            let mut popped = Vec::new();
+            while let Some(x) = heap.pop_max() {
+                check_levels(&heap);
+                popped.push(x);
+            }
+            popped.reverse();
+            assert_eq!(popped, sorted);
+
+            values.iter().for_each(|&x| heap.push(x));
+            let popped: Vec<_> = std::iter::from_fn(|| heap.pop_min()).collect();
+            assert_eq!(popped, sorted);
+        }
+    }
+}
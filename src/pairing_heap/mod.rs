@@ -0,0 +1,376 @@
+use crate::compare::{Compare, MaxComparator, MinComparator};
+use std::mem;
+
+const NIL: usize = usize::MAX;
+
+// Refers to an element while it is in the heap. Popping the element, `clear`
+// and melding the heap into another one invalidate it. The generation tells
+// a stale handle from the element that reused its slot: `get` returns `None`
+// and `decrease_key` panics.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Handle(usize, u64);
+
+// `prev` is the parent for the leftmost child, the left sibling otherwise
+#[derive(Clone, Debug)]
+struct Node<T> {
+    item: Option<T>,
+    // Of the push that filled the slot, starting from 1
+    generation: u64,
+    child: usize,
+    sibling: usize,
+    prev: usize,
+}
+
+// Nodes live in an arena, slots of popped elements are reused by `push`
+#[derive(Clone, Debug)]
+pub struct PairingHeap<T, C = MaxComparator> {
+    nodes: Vec<Node<T>>,
+    free: Vec<usize>,
+    // Last generation handed out, kept by `clear`
+    generation: u64,
+    root: usize,
+    len: usize,
+    pairs: Vec<usize>,
+    cmp: C,
+}
+
+impl<T, C: Compare<T> + Default> PairingHeap<T, C> {
+    #[inline]
+    pub fn new() -> Self {
+        Self::with_comparator(C::default())
+    }
+
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_comparator(capacity, C::default())
+    }
+}
+
+impl<T: Ord> PairingHeap<T, MinComparator> {
+    #[inline]
+    pub fn new_min() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, C: Compare<T>> PairingHeap<T, C> {
+    #[inline]
+    pub fn with_comparator(cmp: C) -> Self {
+        Self::with_capacity_and_comparator(0, cmp)
+    }
+
+    #[inline]
+    pub fn with_capacity_and_comparator(capacity: usize, cmp: C) -> Self {
+        PairingHeap {
+            nodes: Vec::with_capacity(capacity),
+            free: Vec::new(),
+            generation: 0,
+            root: NIL,
+            len: 0,
+            pairs: Vec::new(),
+            cmp,
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    pub fn peek(&self) -> Option<&T> {
+        self.nodes
+            .get(self.root)
+            .and_then(|node| node.item.as_ref())
+    }
+
+    #[inline]
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        self.slot(handle).map(|x| self.item(x))
+    }
+
+    // The slot of a handle whose element is still in the heap
+    #[inline]
+    fn slot(&self, handle: Handle) -> Option<usize> {
+        let node = self.nodes.get(handle.0)?;
+        (node.generation == handle.1 && node.item.is_some()).then_some(handle.0)
+    }
+
+    #[inline]
+    pub fn push(&mut self, item: T) -> Handle {
+        self.generation += 1;
+        let node = Node {
+            item: Some(item),
+            generation: self.generation,
+            child: NIL,
+            sibling: NIL,
+            prev: NIL,
+        };
+        let x = match self.free.pop() {
+            Some(x) => {
+                self.nodes[x] = node;
+                x
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        };
+        self.root = self.link(self.root, x);
+        self.len += 1;
+        Handle(x, self.generation)
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        if self.root == NIL {
+            return None;
+        }
+        let root = self.root;
+        let item = self.nodes[root].item.take();
+        self.free.push(root);
+        self.len -= 1;
+        let child = self.nodes[root].child;
+        self.root = self.merge_pairs(child);
+        item
+    }
+
+    // Replaces the element behind `handle` by one that belongs at least as
+    // high, e.g. a smaller distance in a min-heap
+    pub fn decrease_key(&mut self, handle: Handle, item: T) {
+        let x = self.slot(handle).expect("stale handle");
+        let old = self.item(x);
+        debug_assert!(!self.cmp.gt(old, &item), "the key must not get worse");
+        self.nodes[x].item = Some(item);
+        if x == self.root {
+            return;
+        }
+
+        // Cut the subtree rooted at x and link it with the root again
+        let Node { sibling, prev, .. } = self.nodes[x];
+        if self.nodes[prev].child == x {
+            self.nodes[prev].child = sibling;
+        } else {
+            self.nodes[prev].sibling = sibling;
+        }
+        if sibling != NIL {
+            self.nodes[sibling].prev = prev;
+        }
+        self.nodes[x].sibling = NIL;
+        self.nodes[x].prev = NIL;
+        self.root = self.link(self.root, x);
+    }
+
+    // Moves all elements of `other` into `self`, leaving `other` empty. Handles
+    // into `other` are invalidated.
+    pub fn meld(&mut self, other: &mut Self) {
+        let offset = self.nodes.len();
+        let shift = |x: usize| if x == NIL { NIL } else { x + offset };
+        self.nodes.extend(other.nodes.drain(..).map(|node| Node {
+            item: node.item,
+            generation: node.generation,
+            child: shift(node.child),
+            sibling: shift(node.sibling),
+            prev: shift(node.prev),
+        }));
+        self.free.extend(other.free.drain(..).map(shift));
+        self.generation = self.generation.max(other.generation);
+        self.root = self.link(self.root, shift(other.root));
+        self.len += other.len;
+        other.root = NIL;
+        other.len = 0;
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.free.clear();
+        self.root = NIL;
+        self.len = 0;
+    }
+
+    // Makes the lower root the leftmost child of the higher one
+    #[inline]
+    fn link(&mut self, a: usize, b: usize) -> usize {
+        if a == NIL {
+            return b;
+        }
+        if b == NIL {
+            return a;
+        }
+        let (top, below) = if self.cmp.gt(self.item(b), self.item(a)) {
+            (b, a)
+        } else {
+            (a, b)
+        };
+        let child = self.nodes[top].child;
+        self.nodes[below].sibling = child;
+        self.nodes[below].prev = top;
+        if child != NIL {
+            self.nodes[child].prev = below;
+        }
+        self.nodes[top].child = below;
+        top
+    }
+
+    // Two-pass pairing: link siblings pairwise from the left, then fold the
+    // pairs into one tree from the right
+    fn merge_pairs(&mut self, first: usize) -> usize {
+        let mut pairs = mem::take(&mut self.pairs);
+        let mut x = first;
+        while x != NIL {
+            let y = self.nodes[x].sibling;
+            let next = if y == NIL { NIL } else { self.nodes[y].sibling };
+            self.detach(x);
+            if y != NIL {
+                self.detach(y);
+            }
+            pairs.push(self.link(x, y));
+            x = next;
+        }
+
+        let mut root = pairs.pop().unwrap_or(NIL);
+        while let Some(x) = pairs.pop() {
+            root = self.link(x, root);
+        }
+        self.pairs = pairs;
+        root
+    }
+
+    #[inline]
+    fn detach(&mut self, x: usize) {
+        self.nodes[x].sibling = NIL;
+        self.nodes[x].prev = NIL;
+    }
+
+    #[inline]
+    fn item(&self, x: usize) -> &T {
+        // Every node reachable from the root holds an element
+        self.nodes[x].item.as_ref().unwrap()
+    }
+}
+
+impl<T, C: Compare<T> + Default> Default for PairingHeap<T, C> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+    use std::collections::BinaryHeap;
+
+    #[test]
+    fn test_random_operations() {
+        let mut rng = rand::rng();
+        let mut heap = PairingHeap::<i32>::new();
+        let mut reference = BinaryHeap::new();
+        for _ in 0..if cfg!(miri) { 1000 } else { 100000 } {
+            if rng.random_bool(0.6) {
+                let num = rng.random_range(-1000..1000);
+                heap.push(num);
+                reference.push(num);
+            } else {
+                assert_eq!(heap.pop(), reference.pop());
+            }
+            assert_eq!(heap.len(), reference.len());
+            assert_eq!(heap.peek(), reference.peek());
+        }
+        while let Some(num) = reference.pop() {
+            assert_eq!(heap.pop(), Some(num));
+        }
+        assert_eq!(heap.pop(), None);
+    }
+
+    #[test]
+    fn test_decrease_key() {
+        let mut rng = rand::rng();
+        let mut heap = PairingHeap::<(i32, usize), MinComparator>::new_min();
+        let mut keys = vec![0; 2000];
+        let mut handles = vec![Handle::default(); keys.len()];
+        for (i, key) in keys.iter_mut().enumerate() {
+            *key = rng.random_range(0..100000);
+            handles[i] = heap.push((*key, i));
+        }
+        for _ in 0..5000 {
+            let i = rng.random_range(0..keys.len());
+            keys[i] -= rng.random_range(0..1000);
+            heap.decrease_key(handles[i], (keys[i], i));
+            assert_eq!(heap.get(handles[i]), Some(&(keys[i], i)));
+        }
+
+        let mut expected: Vec<_> = keys.iter().enumerate().map(|(i, &k)| (k, i)).collect();
+        expected.sort_unstable();
+        let popped: Vec<_> = std::iter::from_fn(|| heap.pop()).collect();
+        assert_eq!(popped, expected);
+    }
+
+    #[test]
+    fn test_stale_handle() {
+        let mut heap = PairingHeap::<i32, MinComparator>::new_min();
+        let old = heap.push(5);
+        heap.push(7);
+        assert_eq!(heap.pop(), Some(5));
+        // Reuses the slot of 5
+        let new = heap.push(9);
+        assert_eq!(heap.get(old), None);
+        assert_eq!(heap.get(new), Some(&9));
+        heap.clear();
+        let cleared = heap.push(1);
+        assert_eq!(heap.get(new), None);
+        assert_eq!(heap.get(cleared), Some(&1));
+        assert_eq!(heap.get(Handle::default()), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "stale handle")]
+    fn test_decrease_stale_handle() {
+        let mut heap = PairingHeap::<i32, MinComparator>::new_min();
+        let old = heap.push(5);
+        heap.pop();
+        heap.push(9);
+        heap.decrease_key(old, 1);
+    }
+
+    #[test]
+    fn test_meld() {
+        let mut rng = rand::rng();
+        let mut a = PairingHeap::<i32>::new();
+        let mut b = PairingHeap::<i32>::new();
+        let mut values = Vec::new();
+        for _ in 0..500 {
+            let num = rng.random_range(0..1000);
+            values.push(num);
+            if rng.random_bool(0.5) {
+                a.push(num);
+            } else {
+                b.push(num);
+            }
+        }
+        // Leave free slots in both arenas
+        for heap in [&mut a, &mut b] {
+            for _ in 0..10 {
+                values.remove(values.iter().position(|x| Some(x) == heap.peek()).unwrap());
+                heap.pop();
+            }
+        }
+
+        a.meld(&mut b);
+        assert!(b.is_empty() && b.pop().is_none());
+        assert_eq!(a.len(), values.len());
+        values.extend((0..50).map(|i| i * 7));
+        (0..50).for_each(|i| {
+            a.push(i * 7);
+        });
+        values.sort_unstable_by(|x, y| y.cmp(x));
+        let popped: Vec<_> = std::iter::from_fn(|| a.pop()).collect();
+        assert_eq!(popped, values);
+    }
+}
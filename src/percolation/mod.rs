@@ -9,15 +9,28 @@ use std::{
     time::{Duration, Instant},
 };
 
-struct Percolation<U: UnionFind> {
+// An n-by-n grid of sites, all blocked at first. Rows and columns are
+// numbered from 0, row 0 at the top. The system percolates when an open site
+// in the bottom row is connected to the top row through open neighbours.
+//
+// There are no virtual top and bottom sites, which would make sites joined
+// to the top only through the bottom look full once the system percolates
+// ("backwash"). Instead every root records whether its component touches
+// the top row and the bottom row.
+pub struct Percolation<U: UnionFind> {
     grid: BitVec,
     size: usize,
-    top: usize,
-    bottom: usize,
     last_row: usize,
+    open_sites: usize,
+    percolates: bool,
+    // TOUCHES_TOP | TOUCHES_BOTTOM, only meaningful at roots
+    touches: Vec<u8>,
     uf: U,
 }
 
+const TOUCHES_TOP: u8 = 1;
+const TOUCHES_BOTTOM: u8 = 2;
+
 pub struct PercolationStats<U: UnionFind> {
     size: usize,
     mean: f64,
@@ -26,26 +39,45 @@ pub struct PercolationStats<U: UnionFind> {
     confidence_high: f64,
     trials: usize,
     time: Duration,
+    accesses: u64,
     _phantom: PhantomData<U>,
 }
 
 impl<U: UnionFind> Percolation<U> {
     #[inline]
     pub fn new(size: usize) -> Self {
-        let top = size * size;
-        let bottom = top + 1;
-        let grid = bitvec![0; top];
-        let uf = U::new(bottom + 1);
+        assert!(size > 0, "grid size must be positive");
+        let sites = size * size;
         Self {
-            grid,
+            grid: bitvec![0; sites],
             size,
-            top,
-            bottom,
             last_row: size * (size - 1),
-            uf,
+            open_sites: 0,
+            percolates: false,
+            touches: vec![0; sites],
+            uf: U::new(sites),
         }
     }
 
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    // Flat position of a site, panics when it is outside the grid
+    #[inline]
+    fn position(&self, row: usize, col: usize) -> usize {
+        assert!(
+            row < self.size && col < self.size,
+            "site ({}, {}) is outside the {}-by-{} grid",
+            row,
+            col,
+            self.size,
+            self.size
+        );
+        row * self.size + col
+    }
+
     #[inline]
     fn get_grid(&self, pos: usize) -> BitRef<Const, usize> {
         unsafe { self.grid.get_unchecked(pos) }
@@ -57,54 +89,89 @@ impl<U: UnionFind> Percolation<U> {
     }
 
     #[inline]
-    pub fn is_open(&self, pos: usize) -> bool {
+    pub fn is_open(&self, row: usize, col: usize) -> bool {
+        self.is_open_site(self.position(row, col))
+    }
+
+    #[inline]
+    fn is_open_site(&self, pos: usize) -> bool {
         *self.get_grid(pos)
     }
 
+    // Whether an open site is connected to the top row
+    #[inline]
+    pub fn is_full(&mut self, row: usize, col: usize) -> bool {
+        let pos = self.position(row, col);
+        self.is_open_site(pos) && self.touches[self.uf.find(pos)] & TOUCHES_TOP != 0
+    }
+
     #[inline]
-    pub fn percolates(&mut self) -> bool {
-        self.uf.connected(self.top, self.bottom)
+    pub fn number_of_open_sites(&self) -> usize {
+        self.open_sites
     }
 
     #[inline]
-    pub fn open(&mut self, pos: usize) {
+    pub fn percolates(&self) -> bool {
+        self.percolates
+    }
+
+    // Opening an open site does nothing
+    #[inline]
+    pub fn open(&mut self, row: usize, col: usize) {
+        self.open_site(self.position(row, col));
+    }
+
+    #[inline]
+    fn open_site(&mut self, pos: usize) {
+        if self.is_open_site(pos) {
+            return;
+        }
         *self.get_grid_mut(pos) = true;
+        self.open_sites += 1;
 
-        // Case size = 1 is not intended to be considered
+        // With size = 1 the only site is in both the first and the last row
+        let mut touches = 0;
         if pos < self.size {
-            self.uf.union(pos, self.top);
-            if self.is_open(pos + self.size) {
-                self.uf.union(pos, pos + self.size);
-            }
-        } else {
-            if self.is_open(pos - self.size) {
-                self.uf.union(pos, pos - self.size);
-            }
-            if pos >= self.last_row {
-                self.uf.union(pos, self.bottom);
-            } else if self.is_open(pos + self.size) {
-                self.uf.union(pos, pos + self.size);
-            }
+            touches |= TOUCHES_TOP;
+        } else if self.is_open_site(pos - self.size) {
+            touches |= self.join(pos, pos - self.size);
+        }
+        if pos >= self.last_row {
+            touches |= TOUCHES_BOTTOM;
+        } else if self.is_open_site(pos + self.size) {
+            touches |= self.join(pos, pos + self.size);
         }
 
         let col = pos % self.size;
-        if col == 0 {
-            if self.is_open(pos + 1) {
-                self.uf.union(pos, pos + 1);
-            }
-        } else {
-            if self.is_open(pos - 1) {
-                self.uf.union(pos, pos - 1);
-            }
-            if col != self.size - 1 && self.is_open(pos + 1) {
-                self.uf.union(pos, pos + 1);
-            }
+        if col != 0 && self.is_open_site(pos - 1) {
+            touches |= self.join(pos, pos - 1);
+        }
+        if col != self.size - 1 && self.is_open_site(pos + 1) {
+            touches |= self.join(pos, pos + 1);
+        }
+
+        let root = self.uf.find(pos);
+        self.touches[root] = touches;
+        if touches == TOUCHES_TOP | TOUCHES_BOTTOM {
+            self.percolates = true;
         }
     }
 
+    // Merges the component of an open neighbour into that of pos and
+    // returns what the neighbour's component touched
+    #[inline]
+    fn join(&mut self, pos: usize, neighbour: usize) -> u8 {
+        let touches = self.touches[self.uf.find(neighbour)];
+        self.uf.union(pos, neighbour);
+        touches
+    }
+
     #[inline]
     pub fn reset(&mut self) {
         self.grid.fill(false);
+        self.open_sites = 0;
+        self.percolates = false;
+        self.touches.fill(0);
         self.uf.reset();
     }
 }
@@ -122,10 +189,10 @@ impl<U: UnionFind> PercolationStats<U> {
         results.iter_mut().for_each(|result| {
             while !percolation.percolates() {
                 let mut pos = rng.random_range(0..sq_size);
-                while percolation.is_open(pos) {
+                while percolation.is_open_site(pos) {
                     pos = rng.random_range(0..sq_size);
                 }
-                percolation.open(pos);
+                percolation.open_site(pos);
                 *result += 1.0;
             }
             percolation.reset();
@@ -133,6 +200,7 @@ impl<U: UnionFind> PercolationStats<U> {
         });
 
         let time = start_time.elapsed();
+        let accesses = percolation.uf.accesses();
         let mean = results.iter().sum::<f64>() / trials as f64;
         let stddev =
             (results.iter().map(|&x| (x - mean).powi(2)).sum::<f64>() / (trials - 1) as f64).sqrt();
@@ -147,6 +215,7 @@ impl<U: UnionFind> PercolationStats<U> {
             confidence_high,
             time,
             trials,
+            accesses,
             _phantom: PhantomData,
         }
     }
@@ -163,6 +232,9 @@ impl<U: UnionFind> Display for PercolationStats<U> {
         writeln!(f, "n                       = {}", self.size)?;
         writeln!(f, "trials                  = {}", self.trials)?;
         writeln!(f, "time taken              = {:?}", self.time)?;
+        if self.accesses > 0 {
+            writeln!(f, "array accesses          = {}", self.accesses)?;
+        }
         writeln!(f, "mean                    = {}", self.mean)?;
         writeln!(f, "stddev                  = {}", self.stddev)?;
         writeln!(
@@ -173,3 +245,154 @@ impl<U: UnionFind> Display for PercolationStats<U> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::union_find::{WeightedQuickUnion, WeightedQuickUnionWithPathHalving};
+
+    // Opens the sites marked with '#', one row per line
+    fn from_grid<U: UnionFind>(rows: &[&str]) -> Percolation<U> {
+        let mut percolation = Percolation::new(rows.len());
+        for (row, line) in rows.iter().enumerate() {
+            for (col, c) in line.chars().enumerate() {
+                if c == '#' {
+                    percolation.open(row, col);
+                }
+            }
+        }
+        percolation
+    }
+
+    #[test]
+    fn test_single_site() {
+        let mut percolation = Percolation::<WeightedQuickUnion>::new(1);
+        assert!(!percolation.percolates());
+        assert!(!percolation.is_full(0, 0));
+        percolation.open(0, 0);
+        assert!(percolation.is_open(0, 0));
+        assert!(percolation.is_full(0, 0));
+        assert!(percolation.percolates());
+        assert_eq!(percolation.number_of_open_sites(), 1);
+    }
+
+    #[test]
+    fn test_grids() {
+        let mut percolation = from_grid::<WeightedQuickUnion>(&[
+            ".#...", //
+            ".##..", //
+            "..#.#", //
+            "..###", //
+            "#...#", //
+        ]);
+        assert_eq!(percolation.number_of_open_sites(), 10);
+        assert!(percolation.percolates());
+        assert!(percolation.is_full(3, 3));
+        // Connected to the top only through the bottom row
+        assert!(!percolation.is_full(4, 0));
+        assert!(!percolation.is_full(0, 0));
+
+        let mut percolation = from_grid::<WeightedQuickUnionWithPathHalving>(&[
+            "#.#", //
+            "#.#", //
+            ".#.", //
+        ]);
+        assert!(!percolation.percolates());
+        assert!(percolation.is_full(1, 2));
+        assert!(!percolation.is_full(2, 1));
+        // Opening twice counts once
+        percolation.open(1, 1);
+        percolation.open(1, 1);
+        assert_eq!(percolation.number_of_open_sites(), 6);
+        assert!(percolation.percolates());
+        percolation.reset();
+        assert_eq!(percolation.number_of_open_sites(), 0);
+        assert!(!percolation.percolates());
+    }
+
+    #[test]
+    fn test_no_backwash() {
+        let mut percolation = from_grid::<WeightedQuickUnion>(&[
+            "#...", //
+            "#..#", //
+            "#..#", //
+            "####", //
+        ]);
+        assert!(percolation.percolates());
+        assert!(percolation.is_full(3, 3));
+        assert!(percolation.is_full(1, 3));
+
+        let mut percolation = from_grid::<WeightedQuickUnion>(&[
+            "#...", //
+            "#...", //
+            "#..#", //
+            "#.##", //
+        ]);
+        assert!(percolation.percolates());
+        // The right column only reaches the left one through the bottom row
+        assert!(percolation.is_full(3, 0));
+        assert!(!percolation.is_full(2, 3));
+        assert!(!percolation.is_full(3, 3));
+        assert!(!percolation.is_full(3, 2));
+        // Until the bottom row joins them
+        percolation.open(3, 1);
+        assert!(percolation.is_full(2, 3));
+    }
+
+    // Flood fill from the open sites of the top row
+    fn brute_force_full(open: &[bool], size: usize) -> Vec<bool> {
+        let mut full = vec![false; open.len()];
+        let mut stack: Vec<usize> = (0..size).filter(|&pos| open[pos]).collect();
+        while let Some(pos) = stack.pop() {
+            if full[pos] {
+                continue;
+            }
+            full[pos] = true;
+            let (row, col) = (pos / size, pos % size);
+            let neighbours = [
+                (row > 0).then(|| pos - size),
+                (row + 1 < size).then(|| pos + size),
+                (col > 0).then(|| pos - 1),
+                (col + 1 < size).then(|| pos + 1),
+            ];
+            stack.extend(neighbours.into_iter().flatten().filter(|&q| open[q]));
+        }
+        full
+    }
+
+    #[test]
+    fn test_random_grids() {
+        use rand::Rng;
+        let mut rng = rand::rng();
+        for size in 1..12 {
+            let mut percolation = Percolation::<WeightedQuickUnionWithPathHalving>::new(size);
+            let mut open = vec![false; size * size];
+            for _ in 0..size * size {
+                let (row, col) = (rng.random_range(0..size), rng.random_range(0..size));
+                percolation.open(row, col);
+                open[row * size + col] = true;
+
+                let full = brute_force_full(&open, size);
+                for (pos, &full) in full.iter().enumerate() {
+                    assert_eq!(percolation.is_full(pos / size, pos % size), full);
+                }
+                let percolates = (percolation.last_row..size * size).any(|pos| full[pos]);
+                assert_eq!(percolation.percolates(), percolates);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "outside the 3-by-3 grid")]
+    fn test_out_of_range() {
+        Percolation::<WeightedQuickUnion>::new(3).open(1, 3);
+    }
+
+    #[test]
+    fn test_stats() {
+        let stats = PercolationStats::<WeightedQuickUnion>::new(1, 10);
+        assert_eq!(stats.mean, 1.0);
+        let stats = PercolationStats::<WeightedQuickUnion>::new(20, 50);
+        assert!(0.5 < stats.mean && stats.mean < 0.7);
+    }
+}
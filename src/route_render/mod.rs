@@ -0,0 +1,285 @@
+use crate::graph::{DijkstraQueue, Graph};
+use fnv::FnvHashSet;
+use std::io::{self, Write};
+
+const SVG_WIDTH: f64 = 1600.0;
+const SVG_MARGIN: f64 = 10.0;
+
+pub struct Route {
+    pub start: usize,
+    pub end: usize,
+    pub dist: f64,
+    pub nodes: Vec<usize>,
+}
+
+pub fn write_geojson<
+    W: Write,
+    const CALCPATH: bool,
+    const HEURISTIC: bool,
+    const EARLYSTOP: bool,
+    Q: DijkstraQueue,
+>(
+    out: &mut W,
+    graph: &Graph<CALCPATH, HEURISTIC, EARLYSTOP, Q>,
+    routes: &[Route],
+    settled: &[usize],
+) -> io::Result<()> {
+    let coord = graph.coords();
+    let mut first = true;
+    write!(out, "{{\"type\":\"FeatureCollection\",\"features\":[")?;
+
+    if !settled.is_empty() {
+        write!(
+            out,
+            "\n{{\"type\":\"Feature\",\"properties\":{{\"kind\":\"settled\",\"count\":{}}},\"geometry\":{{\"type\":\"MultiPoint\",\"coordinates\":[",
+            settled.len()
+        )?;
+        write_positions(out, coord, settled.iter().copied())?;
+        write!(out, "]}}}}")?;
+        first = false;
+    }
+
+    for route in routes.iter().filter(|route| route.dist.is_finite()) {
+        if !first {
+            write!(out, ",")?;
+        }
+        first = false;
+        write!(
+            out,
+            "\n{{\"type\":\"Feature\",\"properties\":{{\"kind\":\"route\",\"start\":{},\"end\":{},\"dist\":{}}},",
+            route.start, route.end, route.dist
+        )?;
+        // A LineString needs at least two positions
+        if route.nodes.len() < 2 {
+            let (x, y) = coord[route.start];
+            write!(
+                out,
+                "\"geometry\":{{\"type\":\"Point\",\"coordinates\":[{},{}]}}}}",
+                x, y
+            )?;
+        } else {
+            write!(
+                out,
+                "\"geometry\":{{\"type\":\"LineString\",\"coordinates\":["
+            )?;
+            write_positions(out, coord, route.nodes.iter().copied())?;
+            write!(out, "]}}}}")?;
+        }
+    }
+
+    writeln!(out, "\n]}}")
+}
+
+pub fn write_svg<
+    W: Write,
+    const CALCPATH: bool,
+    const HEURISTIC: bool,
+    const EARLYSTOP: bool,
+    Q: DijkstraQueue,
+>(
+    out: &mut W,
+    graph: &Graph<CALCPATH, HEURISTIC, EARLYSTOP, Q>,
+    routes: &[Route],
+    settled: &[usize],
+) -> io::Result<()> {
+    let coord = graph.coords();
+    let transform = SvgTransform::new(coord);
+
+    writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" viewBox=\"0 0 {:.0} {:.0}\">",
+        transform.width, transform.height, transform.width, transform.height
+    )?;
+    writeln!(out, "<rect width=\"100%\" height=\"100%\" fill=\"white\"/>")?;
+
+    // Undirected edges are usually stored in both directions, draw each once
+    write!(
+        out,
+        "<path fill=\"none\" stroke=\"#bbbbbb\" stroke-width=\"0.5\" d=\""
+    )?;
+    let mut drawn = FnvHashSet::default();
+    for (u, v, _) in graph.edges() {
+        if !drawn.insert((u.min(v), u.max(v))) {
+            continue;
+        }
+        let (x1, y1) = transform.apply(coord[u]);
+        let (x2, y2) = transform.apply(coord[v]);
+        write!(out, "M{:.1} {:.1}L{:.1} {:.1}", x1, y1, x2, y2)?;
+    }
+    writeln!(out, "\"/>")?;
+
+    if !settled.is_empty() {
+        // Zero-length segments with round caps render as dots
+        write!(
+            out,
+            "<path fill=\"none\" stroke=\"#4a90d9\" stroke-opacity=\"0.6\" stroke-width=\"2\" stroke-linecap=\"round\" d=\""
+        )?;
+        for &u in settled {
+            let (x, y) = transform.apply(coord[u]);
+            write!(out, "M{:.1} {:.1}h0", x, y)?;
+        }
+        writeln!(out, "\"/>")?;
+    }
+
+    for route in routes.iter().filter(|route| route.dist.is_finite()) {
+        write!(
+            out,
+            "<polyline fill=\"none\" stroke=\"#d0021b\" stroke-width=\"2\" points=\""
+        )?;
+        for &u in &route.nodes {
+            let (x, y) = transform.apply(coord[u]);
+            write!(out, "{:.1},{:.1} ", x, y)?;
+        }
+        writeln!(
+            out,
+            "\"><title>{} -> {}: {}</title></polyline>",
+            route.start, route.end, route.dist
+        )?;
+    }
+
+    writeln!(out, "</svg>")
+}
+
+fn write_positions<W: Write>(
+    out: &mut W,
+    coord: &[(i32, i32)],
+    nodes: impl Iterator<Item = usize>,
+) -> io::Result<()> {
+    for (i, u) in nodes.enumerate() {
+        let (x, y) = coord[u];
+        if i > 0 {
+            write!(out, ",")?;
+        }
+        write!(out, "[{},{}]", x, y)?;
+    }
+    Ok(())
+}
+
+struct SvgTransform {
+    min_x: f64,
+    max_y: f64,
+    scale: f64,
+    width: f64,
+    height: f64,
+}
+
+impl SvgTransform {
+    fn new(coord: &[(i32, i32)]) -> Self {
+        let (mut min_x, mut min_y) = (i32::MAX, i32::MAX);
+        let (mut max_x, mut max_y) = (i32::MIN, i32::MIN);
+        for &(x, y) in coord {
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+        if coord.is_empty() {
+            (min_x, min_y, max_x, max_y) = (0, 0, 0, 0);
+        }
+
+        let span_x = (max_x - min_x).max(1) as f64;
+        let span_y = (max_y - min_y) as f64;
+        let scale = (SVG_WIDTH - 2.0 * SVG_MARGIN) / span_x;
+        SvgTransform {
+            min_x: min_x as f64,
+            max_y: max_y as f64,
+            scale,
+            width: SVG_WIDTH,
+            height: span_y * scale + 2.0 * SVG_MARGIN,
+        }
+    }
+
+    // SVG's y axis points down
+    #[inline]
+    fn apply(&self, (x, y): (i32, i32)) -> (f64, f64) {
+        (
+            (x as f64 - self.min_x) * self.scale + SVG_MARGIN,
+            (self.max_y - y as f64) * self.scale + SVG_MARGIN,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A 10 by 10 square, 0-1 stored both ways, 1-2 and 2-3 only one way
+    fn square() -> Graph<false, false, false> {
+        let mut graph = Graph::new(4, &[(0, 0), (10, 0), (10, 10), (0, 10)]);
+        graph.add_edge(0, 1);
+        graph.add_edge(1, 0);
+        graph.add_edge(2, 1);
+        graph.add_edge(2, 3);
+        graph
+    }
+
+    fn routes() -> Vec<Route> {
+        vec![
+            Route {
+                start: 0,
+                end: 2,
+                dist: 20.0,
+                nodes: vec![0, 1, 2],
+            },
+            Route {
+                start: 3,
+                end: 3,
+                dist: 0.0,
+                nodes: vec![3],
+            },
+            Route {
+                start: 0,
+                end: 3,
+                dist: f64::INFINITY,
+                nodes: Vec::new(),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_geojson() {
+        let mut out = Vec::new();
+        write_geojson(&mut out, &square(), &routes(), &[0, 1]).unwrap();
+        let json = String::from_utf8(out).unwrap();
+        assert_eq!(json.matches("\"type\":\"Feature\"").count(), 3);
+        assert!(json.contains(
+            "\"kind\":\"settled\",\"count\":2},\"geometry\":{\"type\":\"MultiPoint\",\"coordinates\":[[0,0],[10,0]]}"
+        ));
+        assert!(json.contains(
+            "\"start\":0,\"end\":2,\"dist\":20},\"geometry\":{\"type\":\"LineString\",\"coordinates\":[[0,0],[10,0],[10,10]]}"
+        ));
+        assert!(json.contains(
+            "\"start\":3,\"end\":3,\"dist\":0},\"geometry\":{\"type\":\"Point\",\"coordinates\":[0,10]}"
+        ));
+        // Unreachable routes are left out
+        assert!(!json.contains("\"end\":3,\"dist\":inf"));
+        assert!(json.trim_end().ends_with("]}"));
+
+        let mut out = Vec::new();
+        write_geojson(&mut out, &square(), &[], &[]).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"type\":\"FeatureCollection\",\"features\":[\n]}\n"
+        );
+    }
+
+    #[test]
+    fn test_svg() {
+        let mut out = Vec::new();
+        write_svg(&mut out, &square(), &routes(), &[0, 2]).unwrap();
+        let svg = String::from_utf8(out).unwrap();
+        // 1580 pixels across 10 units, y flipped
+        assert!(svg.contains("width=\"1600\" height=\"1600\""));
+        let edges = svg.lines().find(|line| line.contains("#bbbbbb")).unwrap();
+        assert_eq!(edges.matches('M').count(), 3);
+        assert!(edges.contains("M10.0 1590.0L1590.0 1590.0"));
+        assert!(edges.contains("M1590.0 10.0L1590.0 1590.0"));
+        assert!(edges.contains("M1590.0 10.0L10.0 10.0"));
+        assert!(svg.contains("d=\"M10.0 1590.0h0M1590.0 10.0h0\""));
+        assert!(svg.contains(
+            "points=\"10.0,1590.0 1590.0,1590.0 1590.0,10.0 \"><title>0 -> 2: 20</title>"
+        ));
+        assert_eq!(svg.matches("<polyline").count(), 2);
+        assert!(svg.trim_end().ends_with("</svg>"));
+    }
+}
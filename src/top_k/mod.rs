@@ -0,0 +1,140 @@
+use crate::compare::{Compare, KeyComparator, MaxComparator, Reversed};
+use crate::dary_heap::DaryHeap;
+use std::slice;
+
+// Keeps the k greatest elements according to `C`. The heap is reversed, so
+// the worst kept element is on top and is the one a better offer replaces.
+#[derive(Clone, Debug)]
+pub struct TopK<T, const D: usize, C = MaxComparator> {
+    k: usize,
+    heap: DaryHeap<T, D, Reversed<C>>,
+}
+
+impl<T, const D: usize, C: Compare<T> + Default> TopK<T, D, C> {
+    #[inline]
+    pub fn new(k: usize) -> Self {
+        Self::with_comparator(k, C::default())
+    }
+}
+
+impl<T, const D: usize, K: Ord, F: Fn(&T) -> K> TopK<T, D, KeyComparator<F>> {
+    // Keeps the k elements with the greatest keys
+    #[inline]
+    pub fn by_key(k: usize, f: F) -> Self {
+        Self::with_comparator(k, KeyComparator(f))
+    }
+}
+
+impl<T, const D: usize, C: Compare<T>> TopK<T, D, C> {
+    #[inline]
+    pub fn with_comparator(k: usize, cmp: C) -> Self {
+        TopK {
+            k,
+            heap: DaryHeap::with_capacity_and_comparator(k, Reversed(cmp)),
+        }
+    }
+
+    #[inline]
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    // The element an offer has to beat once k elements are kept
+    #[inline]
+    pub fn worst(&self) -> Option<&T> {
+        self.heap.peek()
+    }
+
+    // Arbitrary order
+    #[inline]
+    pub fn iter(&self) -> slice::Iter<'_, T> {
+        self.heap.iter()
+    }
+
+    // O(log k). Returns whether `item` is kept, ties with the worst kept
+    // element are rejected.
+    #[inline]
+    pub fn offer(&mut self, item: T) -> bool {
+        if self.heap.len() < self.k {
+            self.heap.push(item);
+            return true;
+        }
+        let better = self
+            .heap
+            .peek()
+            .is_some_and(|worst| self.heap.comparator().0.gt(&item, worst));
+        if better {
+            *self.heap.peek_mut().unwrap() = item;
+        }
+        better
+    }
+
+    // Best first
+    #[inline]
+    pub fn into_sorted_vec(self) -> Vec<T> {
+        self.heap.into_sorted_vec()
+    }
+}
+
+impl<T, const D: usize, C: Compare<T>> Extend<T> for TopK<T, D, C> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|item| {
+            self.offer(item);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compare::MinComparator;
+    use rand::Rng;
+
+    #[test]
+    fn test_random_streams() {
+        let mut rng = rand::rng();
+        for k in [0, 1, 2, 5, 64, 1000] {
+            let values: Vec<i32> = (0..500).map(|_| rng.random_range(0..200)).collect();
+            let mut expected = values.clone();
+            expected.sort_unstable_by(|a, b| b.cmp(a));
+            expected.truncate(k);
+
+            let mut top = TopK::<_, 4>::new(k);
+            top.extend(values.iter().copied());
+            assert_eq!(top.len(), k.min(values.len()));
+            assert_eq!(top.worst(), expected.last());
+            assert_eq!(top.into_sorted_vec(), expected);
+
+            let mut expected = values.clone();
+            expected.sort_unstable();
+            expected.truncate(k);
+            let mut top = TopK::<_, 2, MinComparator>::new(k);
+            top.extend(values.iter().copied());
+            assert_eq!(top.into_sorted_vec(), expected);
+        }
+    }
+
+    #[test]
+    fn test_by_key() {
+        let words = ["a", "bbb", "cc", "dddd", "ee", "f"];
+        let mut top = TopK::<_, 3, _>::by_key(3, |word: &&str| word.len());
+        assert!(top.offer(words[0]));
+        words[1..].iter().for_each(|&word| {
+            top.offer(word);
+        });
+        assert_eq!(top.k(), 3);
+        // "ee" ties with the worst kept word "cc" and is rejected
+        assert_eq!(top.into_sorted_vec(), ["dddd", "bbb", "cc"]);
+    }
+}
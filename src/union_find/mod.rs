@@ -1,194 +1,1570 @@
+use fnv::FnvHashMap;
+use std::{
+    cell::Cell,
+    fmt::{self, Debug, Display},
+    hash::Hash,
+    sync::atomic::{AtomicUsize, Ordering},
+    vec,
+};
+
 pub trait UnionFind {
     fn new(size: usize) -> Self;
+    // Adds a singleton component and returns its element, which is the
+    // previous `size()`
+    fn make_set(&mut self) -> usize;
+    // Number of elements
+    fn size(&self) -> usize;
+    // Number of components
+    fn count(&self) -> usize;
     fn find(&mut self, p: usize) -> usize;
     #[inline]
     fn connected(&mut self, p: usize, q: usize) -> bool {
         self.find(p) == self.find(q)
     }
-    fn union(&mut self, p: usize, q: usize);
+    // Number of elements in the component of p
+    fn component_size(&mut self, p: usize) -> usize;
+    // Returns whether p and q were in different components
+    fn union(&mut self, p: usize, q: usize) -> bool;
     fn reset(&mut self);
-}
+    // Array accesses since creation, always 0 unless counting is enabled.
+    // `reset` leaves it alone, so it adds up over repeated runs.
+    fn accesses(&self) -> u64;
+    // Parent of p in the forest, p itself for a root. Not counted as an access.
+    fn parent(&self, p: usize) -> usize;
 
-pub struct QuickUnion {
-    id: Vec<usize>,
+    // Largest number of links from an element up to its root
+    fn height(&self) -> usize {
+        let size = self.size();
+        let mut depth = vec![usize::MAX; size];
+        let mut path = Vec::new();
+        let mut height = 0;
+        for p in 0..size {
+            let mut q = p;
+            while depth[q] == usize::MAX {
+                let parent = self.parent(q);
+                if parent == q {
+                    depth[q] = 0;
+                    break;
+                }
+                path.push(q);
+                q = parent;
+            }
+            let mut d = depth[q];
+            while let Some(r) = path.pop() {
+                d += 1;
+                depth[r] = d;
+            }
+            height = height.max(d);
+        }
+        height
+    }
+
+    // Members of each component in increasing order, the components ordered
+    // by their smallest member
+    fn components(&mut self) -> vec::IntoIter<Vec<usize>> {
+        let size = self.size();
+        let mut index = vec![usize::MAX; size];
+        let mut components: Vec<Vec<usize>> = Vec::with_capacity(self.count());
+        for p in 0..size {
+            let root = self.find(p);
+            if index[root] == usize::MAX {
+                index[root] = components.len();
+                components.push(Vec::with_capacity(self.component_size(root)));
+            }
+            components[index[root]].push(p);
+        }
+        components.into_iter()
+    }
 }
 
-pub struct WeightedQuickUnion {
-    id: Vec<usize>,
-    sz: Vec<usize>,
+// Integer type the parent and size arrays are stored as. `u32` halves their
+// memory, but limits the number of elements.
+pub trait Index: Copy + Eq + Ord + Debug {
+    const MAX_SIZE: usize;
+    fn from_usize(p: usize) -> Self;
+    fn to_usize(self) -> usize;
 }
 
-pub struct WeightedQuickUnionWithPathCompression {
-    id: Vec<usize>,
-    sz: Vec<usize>,
+impl Index for usize {
+    const MAX_SIZE: usize = usize::MAX;
+
+    #[inline]
+    fn from_usize(p: usize) -> Self {
+        p
+    }
+
+    #[inline]
+    fn to_usize(self) -> usize {
+        self
+    }
 }
 
-impl QuickUnion {
+impl Index for u32 {
+    const MAX_SIZE: usize = u32::MAX as usize;
+
     #[inline]
-    fn get_id(&self, p: usize) -> &usize {
-        unsafe { self.id.get_unchecked(p) }
+    fn from_usize(p: usize) -> Self {
+        debug_assert!(p <= u32::MAX as usize);
+        p as u32
+    }
+
+    #[inline]
+    fn to_usize(self) -> usize {
+        self as usize
     }
 }
 
-impl UnionFind for QuickUnion {
+// Counts array accesses when COUNT, compiles to nothing otherwise
+#[derive(Default)]
+struct Counter<const COUNT: bool>(Cell<u64>);
+
+impl<const COUNT: bool> Counter<COUNT> {
     #[inline]
-    fn new(size: usize) -> Self {
-        let id = (0..size).collect();
-        Self { id }
+    fn tick(&self) {
+        if COUNT {
+            self.0.set(self.0.get() + 1);
+        }
     }
 
+    #[inline]
+    fn get(&self) -> u64 {
+        self.0.get()
+    }
+}
+
+// id[p] is the component of p: O(1) find, O(n) union
+pub struct QuickFind<I = usize, const COUNT: bool = false> {
+    id: Vec<I>,
+    sz: Vec<I>,
+    count: usize,
+    accesses: Counter<COUNT>,
+}
+
+// id[p] is the parent of p, the trees are linked without balancing
+pub struct QuickUnion<I = usize, const COUNT: bool = false> {
+    id: Vec<I>,
+    sz: Vec<I>,
+    count: usize,
+    accesses: Counter<COUNT>,
+}
+
+// The smaller tree is linked below the larger one
+pub struct WeightedQuickUnion<I = usize, const COUNT: bool = false> {
+    id: Vec<I>,
+    sz: Vec<I>,
+    count: usize,
+    accesses: Counter<COUNT>,
+}
+
+// Two passes: find the root, then point the whole path at it
+pub struct WeightedQuickUnionWithPathCompression<I = usize, const COUNT: bool = false> {
+    id: Vec<I>,
+    sz: Vec<I>,
+    count: usize,
+    accesses: Counter<COUNT>,
+}
+
+// One pass: every other node on the path skips to its grandparent
+pub struct WeightedQuickUnionWithPathHalving<I = usize, const COUNT: bool = false> {
+    id: Vec<I>,
+    sz: Vec<I>,
+    count: usize,
+    accesses: Counter<COUNT>,
+}
+
+// One pass: every node on the path skips to its grandparent
+pub struct WeightedQuickUnionWithPathSplitting<I = usize, const COUNT: bool = false> {
+    id: Vec<I>,
+    sz: Vec<I>,
+    count: usize,
+    accesses: Counter<COUNT>,
+}
+
+// The tree of lower rank is linked below the other. The rank bounds the
+// height by log2(n), so it fits in a u8 and replaces the sizes of the
+// weighted implementations. Path compression leaves it unchanged.
+pub struct QuickUnionByRankWithPathCompression<I = usize, const COUNT: bool = false> {
+    id: Vec<I>,
+    rank: Vec<u8>,
+    count: usize,
+    accesses: Counter<COUNT>,
+}
+
+// Element accessors, each counted as one array access
+macro_rules! impl_id_accessors {
+    ($($uf:ident),*) => {$(
+        impl<I: Index, const COUNT: bool> $uf<I, COUNT> {
+            #[inline]
+            fn get_id(&self, p: usize) -> usize {
+                self.accesses.tick();
+                unsafe { self.id.get_unchecked(p) }.to_usize()
+            }
+
+            #[inline]
+            fn set_id(&mut self, p: usize, id: usize) {
+                self.accesses.tick();
+                unsafe { *self.id.get_unchecked_mut(p) = I::from_usize(id) };
+            }
+        }
+    )*};
+}
+
+impl_id_accessors!(
+    QuickFind,
+    QuickUnion,
+    WeightedQuickUnion,
+    WeightedQuickUnionWithPathCompression,
+    WeightedQuickUnionWithPathHalving,
+    WeightedQuickUnionWithPathSplitting,
+    QuickUnionByRankWithPathCompression
+);
+
+// Size accessors and growth for the implementations that keep sizes
+macro_rules! impl_sz_accessors {
+    ($($uf:ident),*) => {$(
+        impl<I: Index, const COUNT: bool> $uf<I, COUNT> {
+            #[inline]
+            fn get_sz(&self, p: usize) -> usize {
+                self.accesses.tick();
+                unsafe { self.sz.get_unchecked(p) }.to_usize()
+            }
+
+            #[inline]
+            fn set_sz(&mut self, p: usize, sz: usize) {
+                self.accesses.tick();
+                unsafe { *self.sz.get_unchecked_mut(p) = I::from_usize(sz) };
+            }
+
+            #[inline]
+            fn push(&mut self) -> usize {
+                let p = self.id.len();
+                assert!(p < I::MAX_SIZE, "too many elements for the index type");
+                self.id.push(I::from_usize(p));
+                self.sz.push(I::from_usize(1));
+                self.count += 1;
+                p
+            }
+
+            #[inline]
+            fn init(size: usize) -> (Vec<I>, Vec<I>) {
+                assert!(size <= I::MAX_SIZE, "too many elements for the index type");
+                let id = (0..size).map(I::from_usize).collect();
+                let sz = vec![I::from_usize(1); size];
+                (id, sz)
+            }
+        }
+    )*};
+}
+
+impl_sz_accessors!(
+    QuickFind,
+    QuickUnion,
+    WeightedQuickUnion,
+    WeightedQuickUnionWithPathCompression,
+    WeightedQuickUnionWithPathHalving,
+    WeightedQuickUnionWithPathSplitting
+);
+
+// Linking of two roots for the tree-based implementations
+macro_rules! impl_link {
+    ($($uf:ident),*) => {$(
+        impl<I: Index, const COUNT: bool> $uf<I, COUNT> {
+            // Links the root q below the root p
+            #[inline]
+            fn link(&mut self, rt_p: usize, rt_q: usize) {
+                self.set_id(rt_q, rt_p);
+                let sz = self.get_sz(rt_p) + self.get_sz(rt_q);
+                self.set_sz(rt_p, sz);
+                self.count -= 1;
+            }
+        }
+    )*};
+}
+
+impl_link!(
+    QuickUnion,
+    WeightedQuickUnion,
+    WeightedQuickUnionWithPathCompression,
+    WeightedQuickUnionWithPathHalving,
+    WeightedQuickUnionWithPathSplitting
+);
+
+// Union by size for the weighted implementations
+macro_rules! impl_link_by_size {
+    ($($uf:ident),*) => {$(
+        impl<I: Index, const COUNT: bool> $uf<I, COUNT> {
+            // Links the root of the smaller tree below the other
+            #[inline]
+            fn link_by_size(&mut self, rt_p: usize, rt_q: usize) {
+                if self.get_sz(rt_p) < self.get_sz(rt_q) {
+                    self.link(rt_q, rt_p);
+                } else {
+                    self.link(rt_p, rt_q);
+                }
+            }
+        }
+    )*};
+}
+
+impl_link_by_size!(
+    WeightedQuickUnion,
+    WeightedQuickUnionWithPathCompression,
+    WeightedQuickUnionWithPathHalving,
+    WeightedQuickUnionWithPathSplitting
+);
+
+// The parts every implementation shares, `component_size` only for those
+// that keep sizes
+macro_rules! impl_common {
+    () => {
+        impl_common!(without_sizes);
+
+        #[inline]
+        fn component_size(&mut self, p: usize) -> usize {
+            let root = self.find(p);
+            self.get_sz(root)
+        }
+    };
+    (without_sizes) => {
+        #[inline]
+        fn size(&self) -> usize {
+            self.id.len()
+        }
+
+        #[inline]
+        fn count(&self) -> usize {
+            self.count
+        }
+
+        #[inline]
+        fn accesses(&self) -> u64 {
+            self.accesses.get()
+        }
+
+        #[inline]
+        fn parent(&self, p: usize) -> usize {
+            self.id[p].to_usize()
+        }
+    };
+}
+
+// `new` and `reset` for implementations without extra arrays
+macro_rules! impl_new_reset {
+    () => {
+        #[inline]
+        fn new(size: usize) -> Self {
+            let (id, sz) = Self::init(size);
+            Self {
+                id,
+                sz,
+                count: size,
+                accesses: Counter::default(),
+            }
+        }
+
+        #[inline]
+        fn make_set(&mut self) -> usize {
+            self.push()
+        }
+
+        #[inline]
+        fn reset(&mut self) {
+            self.id
+                .iter_mut()
+                .enumerate()
+                .for_each(|(i, x)| *x = I::from_usize(i));
+            self.sz.fill(I::from_usize(1));
+            self.count = self.id.len();
+        }
+    };
+}
+
+impl<I: Index, const COUNT: bool> UnionFind for QuickFind<I, COUNT> {
+    impl_common!();
+    impl_new_reset!();
+
     #[inline]
     fn find(&mut self, p: usize) -> usize {
-        *self.get_id(p)
+        self.get_id(p)
     }
 
     #[inline]
-    fn union(&mut self, p: usize, q: usize) {
+    fn union(&mut self, p: usize, q: usize) -> bool {
         let rt_p = self.find(p);
         let rt_q = self.find(q);
 
         if rt_p == rt_q {
-            return;
+            return false;
         }
 
-        self.id
-            .iter_mut()
-            .filter(|x| **x == rt_p)
-            .for_each(|x| *x = rt_q);
+        for i in 0..self.id.len() {
+            if self.get_id(i) == rt_p {
+                self.set_id(i, rt_q);
+            }
+        }
+        let sz = self.get_sz(rt_q) + self.get_sz(rt_p);
+        self.set_sz(rt_q, sz);
+        self.count -= 1;
+        true
     }
+}
+
+impl<I: Index, const COUNT: bool> UnionFind for QuickUnion<I, COUNT> {
+    impl_common!();
+    impl_new_reset!();
 
     #[inline]
-    fn reset(&mut self) {
-        self.id.iter_mut().enumerate().for_each(|(i, x)| *x = i);
+    fn find(&mut self, mut p: usize) -> usize {
+        loop {
+            let parent = self.get_id(p);
+            if parent == p {
+                return p;
+            }
+            p = parent;
+        }
+    }
+
+    #[inline]
+    fn union(&mut self, p: usize, q: usize) -> bool {
+        let rt_p = self.find(p);
+        let rt_q = self.find(q);
+        if rt_p == rt_q {
+            return false;
+        }
+
+        self.link(rt_q, rt_p);
+        true
     }
 }
 
-impl WeightedQuickUnion {
+impl<I: Index, const COUNT: bool> UnionFind for WeightedQuickUnion<I, COUNT> {
+    impl_common!();
+    impl_new_reset!();
+
     #[inline]
-    fn get_id(&self, p: usize) -> &usize {
-        unsafe { self.id.get_unchecked(p) }
+    fn find(&mut self, mut p: usize) -> usize {
+        loop {
+            let parent = self.get_id(p);
+            if parent == p {
+                return p;
+            }
+            p = parent;
+        }
     }
 
     #[inline]
-    fn get_id_mut(&mut self, p: usize) -> &mut usize {
-        unsafe { self.id.get_unchecked_mut(p) }
+    fn union(&mut self, p: usize, q: usize) -> bool {
+        let rt_p = self.find(p);
+        let rt_q = self.find(q);
+        if rt_p == rt_q {
+            return false;
+        }
+
+        self.link_by_size(rt_p, rt_q);
+        true
     }
+}
+
+impl<I: Index, const COUNT: bool> UnionFind for WeightedQuickUnionWithPathCompression<I, COUNT> {
+    impl_common!();
+    impl_new_reset!();
 
     #[inline]
-    fn get_sz(&self, p: usize) -> &usize {
-        unsafe { self.sz.get_unchecked(p) }
+    fn find(&mut self, mut p: usize) -> usize {
+        let mut root = p;
+        loop {
+            let parent = self.get_id(root);
+            if parent == root {
+                break;
+            }
+            root = parent;
+        }
+        while p != root {
+            let next = self.get_id(p);
+            self.set_id(p, root);
+            p = next;
+        }
+        root
     }
 
     #[inline]
-    fn get_sz_mut(&mut self, p: usize) -> &mut usize {
-        unsafe { self.sz.get_unchecked_mut(p) }
+    fn union(&mut self, p: usize, q: usize) -> bool {
+        let rt_p = self.find(p);
+        let rt_q = self.find(q);
+        if rt_p == rt_q {
+            return false;
+        }
+
+        self.link_by_size(rt_p, rt_q);
+        true
     }
 }
 
-impl UnionFind for WeightedQuickUnion {
+impl<I: Index, const COUNT: bool> UnionFind for WeightedQuickUnionWithPathHalving<I, COUNT> {
+    impl_common!();
+    impl_new_reset!();
+
     #[inline]
-    fn new(size: usize) -> Self {
-        let id = (0..size).collect();
-        let sz = vec![1; size];
-        Self { id, sz }
+    fn find(&mut self, mut p: usize) -> usize {
+        loop {
+            let parent = self.get_id(p);
+            if parent == p {
+                return p;
+            }
+            let grandparent = self.get_id(parent);
+            self.set_id(p, grandparent);
+            p = grandparent;
+        }
     }
 
+    #[inline]
+    fn union(&mut self, p: usize, q: usize) -> bool {
+        let rt_p = self.find(p);
+        let rt_q = self.find(q);
+        if rt_p == rt_q {
+            return false;
+        }
+
+        self.link_by_size(rt_p, rt_q);
+        true
+    }
+}
+
+impl<I: Index, const COUNT: bool> UnionFind for WeightedQuickUnionWithPathSplitting<I, COUNT> {
+    impl_common!();
+    impl_new_reset!();
+
     #[inline]
     fn find(&mut self, mut p: usize) -> usize {
-        while p != *self.get_id(p) {
-            p = *self.get_id(p);
+        loop {
+            let parent = self.get_id(p);
+            if parent == p {
+                return p;
+            }
+            let grandparent = self.get_id(parent);
+            self.set_id(p, grandparent);
+            p = parent;
+        }
+    }
+
+    #[inline]
+    fn union(&mut self, p: usize, q: usize) -> bool {
+        let rt_p = self.find(p);
+        let rt_q = self.find(q);
+        if rt_p == rt_q {
+            return false;
+        }
+
+        self.link_by_size(rt_p, rt_q);
+        true
+    }
+}
+
+impl<I: Index, const COUNT: bool> QuickUnionByRankWithPathCompression<I, COUNT> {
+    // Links the root q below the root p
+    #[inline]
+    fn link(&mut self, rt_p: usize, rt_q: usize) {
+        self.set_id(rt_q, rt_p);
+        self.count -= 1;
+    }
+
+    #[inline]
+    fn get_rank(&self, p: usize) -> u8 {
+        self.accesses.tick();
+        unsafe { *self.rank.get_unchecked(p) }
+    }
+
+    #[inline]
+    fn set_rank(&mut self, p: usize, rank: u8) {
+        self.accesses.tick();
+        unsafe { *self.rank.get_unchecked_mut(p) = rank };
+    }
+}
+
+impl<I: Index, const COUNT: bool> UnionFind for QuickUnionByRankWithPathCompression<I, COUNT> {
+    impl_common!(without_sizes);
+
+    #[inline]
+    fn new(size: usize) -> Self {
+        assert!(size <= I::MAX_SIZE, "too many elements for the index type");
+        Self {
+            id: (0..size).map(I::from_usize).collect(),
+            rank: vec![0; size],
+            count: size,
+            accesses: Counter::default(),
         }
+    }
+
+    #[inline]
+    fn make_set(&mut self) -> usize {
+        let p = self.id.len();
+        assert!(p < I::MAX_SIZE, "too many elements for the index type");
+        self.id.push(I::from_usize(p));
+        self.rank.push(0);
+        self.count += 1;
         p
     }
 
+    // No sizes are kept, O(n)
+    fn component_size(&mut self, p: usize) -> usize {
+        let root = self.find(p);
+        (0..self.id.len()).filter(|&q| self.find(q) == root).count()
+    }
+
     #[inline]
-    fn union(&mut self, p: usize, q: usize) {
+    fn find(&mut self, mut p: usize) -> usize {
+        let mut root = p;
+        loop {
+            let parent = self.get_id(root);
+            if parent == root {
+                break;
+            }
+            root = parent;
+        }
+        while p != root {
+            let next = self.get_id(p);
+            self.set_id(p, root);
+            p = next;
+        }
+        root
+    }
+
+    #[inline]
+    fn union(&mut self, p: usize, q: usize) -> bool {
         let rt_p = self.find(p);
         let rt_q = self.find(q);
         if rt_p == rt_q {
-            return;
+            return false;
         }
 
-        if self.get_sz(rt_p) < self.get_sz(rt_q) {
-            *self.get_id_mut(rt_p) = rt_q;
-            *self.get_sz_mut(rt_q) += *self.get_sz(rt_p);
+        let rank_p = self.get_rank(rt_p);
+        let rank_q = self.get_rank(rt_q);
+        if rank_p < rank_q {
+            self.link(rt_q, rt_p);
         } else {
-            *self.get_id_mut(rt_q) = rt_p;
-            *self.get_sz_mut(rt_p) += *self.get_sz(rt_q);
+            if rank_p == rank_q {
+                self.set_rank(rt_p, rank_p + 1);
+            }
+            self.link(rt_p, rt_q);
+        }
+        true
+    }
+
+    #[inline]
+    fn reset(&mut self) {
+        self.id
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, x)| *x = I::from_usize(i));
+        self.rank.fill(0);
+        self.count = self.id.len();
+    }
+}
+
+// Union by size without path compression, so every union only changes one
+// parent and can be undone. Unions are logged, `rollback` pops them.
+#[derive(Clone, Debug)]
+pub struct RollbackUnionFind {
+    id: Vec<usize>,
+    sz: Vec<usize>,
+    count: usize,
+    // Roots that were linked below another root, oldest first
+    history: Vec<usize>,
+}
+
+impl RollbackUnionFind {
+    // The point `rollback` returns to
+    #[inline]
+    pub fn snapshot(&self) -> usize {
+        self.history.len()
+    }
+
+    // Undoes the unions made since `snapshot` returned `to`
+    pub fn rollback(&mut self, to: usize) {
+        assert!(
+            to <= self.history.len(),
+            "snapshot is newer than the history"
+        );
+        while self.history.len() > to {
+            let child = self.history.pop().unwrap();
+            let parent = self.id[child];
+            self.sz[parent] -= self.sz[child];
+            self.id[child] = child;
+            self.count += 1;
+        }
+    }
+}
+
+impl UnionFind for RollbackUnionFind {
+    #[inline]
+    fn new(size: usize) -> Self {
+        Self {
+            id: (0..size).collect(),
+            sz: vec![1; size],
+            count: size,
+            history: Vec::new(),
+        }
+    }
+
+    #[inline]
+    fn make_set(&mut self) -> usize {
+        let p = self.id.len();
+        self.id.push(p);
+        self.sz.push(1);
+        self.count += 1;
+        p
+    }
+
+    #[inline]
+    fn size(&self) -> usize {
+        self.id.len()
+    }
+
+    #[inline]
+    fn count(&self) -> usize {
+        self.count
+    }
+
+    #[inline]
+    fn find(&mut self, mut p: usize) -> usize {
+        while p != self.id[p] {
+            p = self.id[p];
+        }
+        p
+    }
+
+    #[inline]
+    fn component_size(&mut self, p: usize) -> usize {
+        let root = self.find(p);
+        self.sz[root]
+    }
+
+    #[inline]
+    fn union(&mut self, p: usize, q: usize) -> bool {
+        let mut rt_p = self.find(p);
+        let mut rt_q = self.find(q);
+        if rt_p == rt_q {
+            return false;
         }
+
+        if self.sz[rt_p] < self.sz[rt_q] {
+            (rt_p, rt_q) = (rt_q, rt_p);
+        }
+        self.id[rt_q] = rt_p;
+        self.sz[rt_p] += self.sz[rt_q];
+        self.count -= 1;
+        self.history.push(rt_q);
+        true
     }
 
+    // Also forgets the history
     #[inline]
     fn reset(&mut self) {
         self.id.iter_mut().enumerate().for_each(|(i, x)| *x = i);
         self.sz.fill(1);
+        self.count = self.id.len();
+        self.history.clear();
+    }
+
+    #[inline]
+    fn accesses(&self) -> u64 {
+        0
+    }
+
+    #[inline]
+    fn parent(&self, p: usize) -> usize {
+        self.id[p]
     }
 }
 
-impl WeightedQuickUnionWithPathCompression {
+// Lock-free union-find that many threads can share through `&self`. Roots
+// are linked in a random total order, lower priority below higher, so the
+// trees stay shallow in expectation without tracking sizes or ranks, and
+// `find` halves paths with CAS. Components only ever merge, so an answer of
+// `connected` that is true stays true.
+#[derive(Debug)]
+pub struct AtomicUnionFind {
+    id: Vec<AtomicUsize>,
+    count: AtomicUsize,
+    seed: u64,
+}
+
+impl AtomicUnionFind {
+    pub fn new(size: usize) -> Self {
+        Self {
+            id: (0..size).map(AtomicUsize::new).collect(),
+            count: AtomicUsize::new(size),
+            seed: rand::random(),
+        }
+    }
+
+    // Number of elements
     #[inline]
-    fn get_id(&self, p: usize) -> &usize {
-        unsafe { self.id.get_unchecked(p) }
+    pub fn size(&self) -> usize {
+        self.id.len()
     }
 
+    // Number of components
     #[inline]
-    fn get_id_mut(&mut self, p: usize) -> &mut usize {
-        unsafe { self.id.get_unchecked_mut(p) }
+    pub fn count(&self) -> usize {
+        self.count.load(Ordering::Acquire)
     }
 
     #[inline]
-    fn get_sz(&self, p: usize) -> &usize {
-        unsafe { self.sz.get_unchecked(p) }
+    fn parent(&self, p: usize) -> usize {
+        self.id[p].load(Ordering::Acquire)
     }
 
+    // Linking order, a hash of p with ties broken by p
     #[inline]
-    fn get_sz_mut(&mut self, p: usize) -> &mut usize {
-        unsafe { self.sz.get_unchecked_mut(p) }
+    fn priority(&self, p: usize) -> (u64, usize) {
+        let mut x = (p as u64) ^ self.seed;
+        x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+        (x ^ (x >> 31), p)
+    }
+
+    // The root of p at some point during the call
+    pub fn find(&self, mut p: usize) -> usize {
+        loop {
+            let parent = self.parent(p);
+            if parent == p {
+                return p;
+            }
+            let grandparent = self.parent(parent);
+            if parent != grandparent {
+                // Losing the race only means another thread moved p up
+                let _ = self.id[p].compare_exchange_weak(
+                    parent,
+                    grandparent,
+                    Ordering::AcqRel,
+                    Ordering::Acquire,
+                );
+            }
+            p = grandparent;
+        }
+    }
+
+    pub fn connected(&self, p: usize, q: usize) -> bool {
+        loop {
+            let rt_p = self.find(p);
+            let rt_q = self.find(q);
+            if rt_p == rt_q {
+                return true;
+            }
+            // rt_p may have been linked below rt_q meanwhile
+            if self.parent(rt_p) == rt_p {
+                return false;
+            }
+        }
+    }
+
+    // Returns whether this call merged two components
+    pub fn union(&self, p: usize, q: usize) -> bool {
+        loop {
+            let mut rt_p = self.find(p);
+            let mut rt_q = self.find(q);
+            if rt_p == rt_q {
+                return false;
+            }
+            if self.priority(rt_p) > self.priority(rt_q) {
+                (rt_p, rt_q) = (rt_q, rt_p);
+            }
+            // Fails if rt_p stopped being a root, then retry from the top
+            if self.id[rt_p]
+                .compare_exchange(rt_p, rt_q, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+            {
+                self.count.fetch_sub(1, Ordering::AcqRel);
+                return true;
+            }
+        }
     }
 }
 
-impl UnionFind for WeightedQuickUnionWithPathCompression {
+// For exclusive use, e.g. queries once the threads are done
+impl UnionFind for AtomicUnionFind {
     #[inline]
     fn new(size: usize) -> Self {
-        let id = (0..size).collect();
-        let sz = vec![1; size];
-        Self { id, sz }
+        AtomicUnionFind::new(size)
     }
 
     #[inline]
-    fn find(&mut self, mut p: usize) -> usize {
+    fn make_set(&mut self) -> usize {
+        let p = self.id.len();
+        self.id.push(AtomicUsize::new(p));
+        *self.count.get_mut() += 1;
+        p
+    }
+
+    #[inline]
+    fn size(&self) -> usize {
+        AtomicUnionFind::size(self)
+    }
+
+    #[inline]
+    fn count(&self) -> usize {
+        AtomicUnionFind::count(self)
+    }
+
+    #[inline]
+    fn find(&mut self, p: usize) -> usize {
+        AtomicUnionFind::find(self, p)
+    }
+
+    #[inline]
+    fn connected(&mut self, p: usize, q: usize) -> bool {
+        AtomicUnionFind::connected(self, p, q)
+    }
+
+    // No sizes are kept, O(n)
+    fn component_size(&mut self, p: usize) -> usize {
+        let root = AtomicUnionFind::find(self, p);
+        (0..self.id.len())
+            .filter(|&q| AtomicUnionFind::find(self, q) == root)
+            .count()
+    }
+
+    #[inline]
+    fn union(&mut self, p: usize, q: usize) -> bool {
+        AtomicUnionFind::union(self, p, q)
+    }
+
+    #[inline]
+    fn reset(&mut self) {
+        self.id
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, x)| *x.get_mut() = i);
+        *self.count.get_mut() = self.id.len();
+    }
+
+    #[inline]
+    fn accesses(&self) -> u64 {
+        0
+    }
+
+    #[inline]
+    fn parent(&self, p: usize) -> usize {
+        AtomicUnionFind::parent(self, p)
+    }
+}
+
+// Abelian group of the potentials in `PotentialUnionFind`
+pub trait Group: Copy + Eq + Debug {
+    const ZERO: Self;
+    fn add(self, other: Self) -> Self;
+    fn neg(self) -> Self;
+
+    #[inline]
+    fn sub(self, other: Self) -> Self {
+        self.add(other.neg())
+    }
+}
+
+// Offsets, "x - y = d"
+impl Group for i64 {
+    const ZERO: Self = 0;
+
+    #[inline]
+    fn add(self, other: Self) -> Self {
+        self + other
+    }
+
+    #[inline]
+    fn neg(self) -> Self {
+        -self
+    }
+}
+
+// Parities under XOR, "x and y are on different sides" is x - y = true
+impl Group for bool {
+    const ZERO: Self = false;
+
+    #[inline]
+    fn add(self, other: Self) -> Self {
+        self ^ other
+    }
+
+    #[inline]
+    fn neg(self) -> Self {
+        self
+    }
+}
+
+// A constraint that disagrees with the ones before it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Contradiction<G> {
+    // The difference the constraint asked for
+    pub expected: G,
+    // The difference the earlier constraints imply
+    pub actual: G,
+}
+
+impl<G: Debug> Display for Contradiction<G> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "expected a difference of {:?}, but it is already {:?}",
+            self.expected, self.actual
+        )
+    }
+}
+
+impl<G: Debug> std::error::Error for Contradiction<G> {}
+
+// Weighted quick-union with path compression where every element carries a
+// potential in G, known relative to the other elements of its component.
+// pot[p] is the potential of p minus that of its parent.
+#[derive(Clone, Debug)]
+pub struct PotentialUnionFind<G> {
+    id: Vec<usize>,
+    sz: Vec<usize>,
+    pot: Vec<G>,
+    count: usize,
+}
+
+impl<G: Group> PotentialUnionFind<G> {
+    pub fn new(size: usize) -> Self {
+        Self {
+            id: (0..size).collect(),
+            sz: vec![1; size],
+            pot: vec![G::ZERO; size],
+            count: size,
+        }
+    }
+
+    #[inline]
+    pub fn make_set(&mut self) -> usize {
+        let p = self.id.len();
+        self.id.push(p);
+        self.sz.push(1);
+        self.pot.push(G::ZERO);
+        self.count += 1;
+        p
+    }
+
+    // Number of elements
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.id.len()
+    }
+
+    // Number of components
+    #[inline]
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn find(&mut self, mut p: usize) -> usize {
         let mut root = p;
-        while root != *self.get_id(root) {
-            root = *self.get_id(root);
+        let mut total = G::ZERO;
+        while root != self.id[root] {
+            total = total.add(self.pot[root]);
+            root = self.id[root];
         }
+        // `total` is the potential of p relative to the root, each node of
+        // the path is pointed at the root with its own
         while p != root {
-            let next = *self.get_id(p);
-            *self.get_id_mut(p) = root;
+            let next = self.id[p];
+            let pot = self.pot[p];
+            self.id[p] = root;
+            self.pot[p] = total;
+            total = total.sub(pot);
             p = next;
         }
         root
     }
 
     #[inline]
-    fn union(&mut self, p: usize, q: usize) {
+    pub fn connected(&mut self, p: usize, q: usize) -> bool {
+        self.find(p) == self.find(q)
+    }
+
+    #[inline]
+    pub fn component_size(&mut self, p: usize) -> usize {
+        let root = self.find(p);
+        self.sz[root]
+    }
+
+    // Potential of p minus that of its root
+    #[inline]
+    fn potential(&mut self, p: usize) -> G {
+        self.find(p);
+        self.pot[p]
+    }
+
+    // The potential of p minus that of q, if they are connected
+    pub fn diff(&mut self, p: usize, q: usize) -> Option<G> {
+        if !self.connected(p, q) {
+            return None;
+        }
+        Some(self.potential(p).sub(self.potential(q)))
+    }
+
+    // Records "p - q = d". Returns whether p and q were in different
+    // components, or the contradiction if they were already related otherwise.
+    pub fn union(&mut self, p: usize, q: usize, d: G) -> Result<bool, Contradiction<G>> {
         let rt_p = self.find(p);
         let rt_q = self.find(q);
+        let pot_p = self.pot[p];
+        let pot_q = self.pot[q];
         if rt_p == rt_q {
-            return;
+            let actual = pot_p.sub(pot_q);
+            return if actual == d {
+                Ok(false)
+            } else {
+                Err(Contradiction {
+                    expected: d,
+                    actual,
+                })
+            };
         }
 
-        if self.get_sz(rt_p) < self.get_sz(rt_q) {
-            *self.get_id_mut(rt_p) = rt_q;
-            *self.get_sz_mut(rt_q) += *self.get_sz(rt_p);
+        // rt_q - rt_p = (p - rt_p) - (q - rt_q) - (p - q)
+        let pot_q_root = pot_p.sub(pot_q).sub(d);
+        if self.sz[rt_p] < self.sz[rt_q] {
+            self.id[rt_p] = rt_q;
+            self.pot[rt_p] = pot_q_root.neg();
+            self.sz[rt_q] += self.sz[rt_p];
         } else {
-            *self.get_id_mut(rt_q) = rt_p;
-            *self.get_sz_mut(rt_p) += *self.get_sz(rt_q);
+            self.id[rt_q] = rt_p;
+            self.pot[rt_q] = pot_q_root;
+            self.sz[rt_p] += self.sz[rt_q];
         }
+        self.count -= 1;
+        Ok(true)
     }
+}
 
+// Union-find over arbitrary keys, which get ids on first sight
+pub struct KeyedUnionFind<K, U = WeightedQuickUnionWithPathHalving> {
+    ids: FnvHashMap<K, usize>,
+    keys: Vec<K>,
+    uf: U,
+}
+
+impl<K: Hash + Eq + Clone, U: UnionFind> KeyedUnionFind<K, U> {
     #[inline]
-    fn reset(&mut self) {
-        self.id.iter_mut().enumerate().for_each(|(i, x)| *x = i);
-        self.sz.fill(1);
+    pub fn new() -> Self {
+        Self {
+            ids: FnvHashMap::default(),
+            keys: Vec::new(),
+            uf: U::new(0),
+        }
+    }
+
+    // Number of keys
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    // Number of components
+    #[inline]
+    pub fn count(&self) -> usize {
+        self.uf.count()
+    }
+
+    // The id of `key`, which becomes a singleton component if it is new
+    pub fn add(&mut self, key: K) -> usize {
+        if let Some(&id) = self.ids.get(&key) {
+            return id;
+        }
+        let id = self.uf.make_set();
+        self.ids.insert(key.clone(), id);
+        self.keys.push(key);
+        id
+    }
+
+    #[inline]
+    pub fn id(&self, key: &K) -> Option<usize> {
+        self.ids.get(key).copied()
+    }
+
+    #[inline]
+    pub fn key(&self, id: usize) -> &K {
+        &self.keys[id]
+    }
+
+    // The representative key of the component of `key`
+    #[inline]
+    pub fn find(&mut self, key: &K) -> Option<&K> {
+        let id = self.id(key)?;
+        let root = self.uf.find(id);
+        Some(&self.keys[root])
+    }
+
+    // A key that was never added is only connected to itself
+    pub fn connected(&mut self, p: &K, q: &K) -> bool {
+        match (self.id(p), self.id(q)) {
+            (Some(p), Some(q)) => self.uf.connected(p, q),
+            _ => p == q,
+        }
+    }
+
+    #[inline]
+    pub fn component_size(&mut self, key: &K) -> Option<usize> {
+        let id = self.id(key)?;
+        Some(self.uf.component_size(id))
+    }
+
+    // Adds the keys that are new. Returns whether p and q were in different
+    // components.
+    #[inline]
+    pub fn union(&mut self, p: K, q: K) -> bool {
+        let p = self.add(p);
+        let q = self.add(q);
+        self.uf.union(p, q)
+    }
+
+    // Keys of each component, in the order they were added
+    pub fn components(&mut self) -> impl Iterator<Item = Vec<&K>> {
+        self.uf
+            .components()
+            .map(|component| component.into_iter().map(|id| &self.keys[id]).collect())
+    }
+}
+
+impl<K: Hash + Eq + Clone, U: UnionFind> Default for KeyedUnionFind<K, U> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    // Against a naive labelling that relabels one whole component per union
+    fn check_random_unions<U: UnionFind>() {
+        const N: usize = 200;
+        let mut rng = rand::rng();
+        let mut uf = U::new(N);
+        let mut label: Vec<usize> = (0..N).collect();
+        for round in 0..2 {
+            for _ in 0..N {
+                let p = rng.random_range(0..N);
+                let q = rng.random_range(0..N);
+                let (lp, lq) = (label[p], label[q]);
+                assert_eq!(uf.connected(p, q), lp == lq);
+                assert_eq!(uf.union(p, q), lp != lq);
+                label.iter_mut().filter(|l| **l == lq).for_each(|l| *l = lp);
+
+                let size = label.iter().filter(|&&l| l == lp).count();
+                assert_eq!(uf.component_size(q), size);
+                let mut labels = label.clone();
+                labels.sort_unstable();
+                labels.dedup();
+                assert_eq!(uf.count(), labels.len());
+            }
+
+            let components: Vec<_> = uf.components().collect();
+            assert_eq!(components.len(), uf.count());
+            assert_eq!(components.iter().map(Vec::len).sum::<usize>(), uf.size());
+            for component in &components {
+                assert!(component.is_sorted());
+                assert!(component.iter().all(|&p| label[p] == label[component[0]]));
+            }
+            assert!(components.windows(2).all(|pair| pair[0][0] < pair[1][0]));
+
+            if round == 0 {
+                uf.reset();
+                label = (0..N).collect();
+                assert_eq!(uf.count(), N);
+            }
+        }
+    }
+
+    #[test]
+    fn test_random_unions() {
+        check_random_unions::<QuickFind>();
+        check_random_unions::<QuickUnion>();
+        check_random_unions::<WeightedQuickUnion>();
+        check_random_unions::<WeightedQuickUnionWithPathCompression>();
+        check_random_unions::<WeightedQuickUnionWithPathHalving>();
+        check_random_unions::<WeightedQuickUnionWithPathSplitting>();
+        check_random_unions::<QuickUnionByRankWithPathCompression>();
+        check_random_unions::<QuickFind<u32>>();
+        check_random_unions::<WeightedQuickUnionWithPathHalving<u32>>();
+        check_random_unions::<QuickUnionByRankWithPathCompression<u32>>();
+        check_random_unions::<RollbackUnionFind>();
+        check_random_unions::<AtomicUnionFind>();
+    }
+
+    fn check_make_set<U: UnionFind>() {
+        let mut uf = U::new(2);
+        assert_eq!(uf.make_set(), 2);
+        assert_eq!(uf.make_set(), 3);
+        assert_eq!((uf.size(), uf.count()), (4, 4));
+        assert!(uf.union(3, 0));
+        assert_eq!(uf.make_set(), 4);
+        assert!(uf.union(4, 0));
+        assert!(uf.connected(3, 4));
+        assert_eq!(uf.component_size(4), 3);
+        assert_eq!(uf.count(), 3);
+
+        let mut uf = U::new(0);
+        (0..100).for_each(|p| assert_eq!(uf.make_set(), p));
+        (1..100).for_each(|p| {
+            uf.union(p - 1, p);
+        });
+        assert_eq!(
+            uf.components().collect::<Vec<_>>(),
+            [(0..100).collect::<Vec<_>>()]
+        );
+    }
+
+    #[test]
+    fn test_make_set() {
+        check_make_set::<QuickFind>();
+        check_make_set::<QuickUnion<u32>>();
+        check_make_set::<WeightedQuickUnion>();
+        check_make_set::<WeightedQuickUnionWithPathCompression>();
+        check_make_set::<WeightedQuickUnionWithPathHalving<u32>>();
+        check_make_set::<WeightedQuickUnionWithPathSplitting>();
+        check_make_set::<QuickUnionByRankWithPathCompression>();
+        check_make_set::<RollbackUnionFind>();
+        check_make_set::<AtomicUnionFind>();
+    }
+
+    // Pairs up the components of equal size in rounds, the worst case of
+    // union by size
+    fn check_height<U: UnionFind>(chain: usize, balanced: usize) {
+        let mut uf = U::new(16);
+        assert_eq!(uf.height(), 0);
+        (1..16).for_each(|p| {
+            uf.union(p - 1, p);
+        });
+        assert_eq!(uf.height(), chain);
+
+        let mut uf = U::new(16);
+        for step in [1, 2, 4, 8] {
+            (0..16).step_by(2 * step).for_each(|p| {
+                uf.union(p, p + step);
+            });
+        }
+        assert_eq!(uf.height(), balanced);
+        assert_eq!(uf.count(), 1);
+    }
+
+    #[test]
+    fn test_height() {
+        check_height::<QuickFind>(1, 1);
+        check_height::<QuickUnion>(15, 4);
+        check_height::<WeightedQuickUnion<u32>>(1, 4);
+        check_height::<RollbackUnionFind>(1, 4);
+
+        let mut uf: WeightedQuickUnionWithPathCompression = UnionFind::new(16);
+        for step in [1, 2, 4, 8] {
+            (0..16).step_by(2 * step).for_each(|p| {
+                uf.union(p, p + step);
+            });
+        }
+        let depth = |uf: &WeightedQuickUnionWithPathCompression, mut p: usize| {
+            let mut depth = 0;
+            while p != uf.parent(p) {
+                p = uf.parent(p);
+                depth += 1;
+            }
+            depth
+        };
+        // The tree is binomial, compressing its only path of length 4 leaves 3
+        let deepest = (0..16).find(|&p| depth(&uf, p) == 4);
+        uf.find(deepest.unwrap());
+        assert_eq!(uf.height(), 3);
+    }
+
+    #[test]
+    fn test_concurrent_unions() {
+        const N: usize = 20000;
+        const THREADS: usize = 8;
+        let mut rng = rand::rng();
+        // Few enough edges that many components remain
+        let edges: Vec<(usize, usize)> = (0..N * 3 / 4)
+            .map(|_| (rng.random_range(0..N), rng.random_range(0..N)))
+            .collect();
+
+        let uf = AtomicUnionFind::new(N);
+        let merged: usize = std::thread::scope(|scope| {
+            let handles: Vec<_> = edges
+                .chunks(edges.len().div_ceil(THREADS))
+                .map(|chunk| {
+                    let uf = &uf;
+                    scope.spawn(move || {
+                        let mut merged = 0;
+                        for &(p, q) in chunk {
+                            merged += uf.union(p, q) as usize;
+                            // Visible to this thread right away, and for good
+                            assert!(uf.connected(p, q));
+                        }
+                        assert!(chunk.iter().all(|&(p, q)| uf.connected(q, p)));
+                        merged
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .sum()
+        });
+
+        let mut expected: WeightedQuickUnion = UnionFind::new(N);
+        edges.iter().for_each(|&(p, q)| {
+            expected.union(p, q);
+        });
+        assert_eq!(uf.count(), expected.count());
+        assert_eq!(N - merged, expected.count());
+        for _ in 0..N {
+            let p = rng.random_range(0..N);
+            let q = rng.random_range(0..N);
+            assert_eq!(uf.connected(p, q), expected.connected(p, q));
+        }
+        let mut uf = uf;
+        let components: Vec<_> = uf.components().collect();
+        assert_eq!(components, expected.components().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_rollback() {
+        let mut uf = RollbackUnionFind::new(6);
+        uf.union(0, 1);
+        let first = uf.snapshot();
+        uf.union(2, 3);
+        uf.union(1, 3);
+        // Already connected, nothing to log
+        assert!(!uf.union(0, 2));
+        let second = uf.snapshot();
+        uf.union(4, 5);
+        uf.union(5, 0);
+        assert_eq!((uf.count(), uf.component_size(4)), (1, 6));
+
+        uf.rollback(second);
+        assert_eq!((uf.count(), uf.component_size(0)), (3, 4));
+        assert!(uf.connected(0, 3) && !uf.connected(0, 4));
+        uf.rollback(first);
+        assert_eq!(uf.count(), 5);
+        assert!(uf.connected(0, 1) && !uf.connected(1, 2));
+        assert_eq!(uf.component_size(2), 1);
+        uf.rollback(0);
+        assert_eq!(uf.components().count(), 6);
+    }
+
+    #[test]
+    fn test_potentials() {
+        const N: usize = 100;
+        let mut rng = rand::rng();
+        // Hidden values, every consistent constraint is derived from them
+        let values: Vec<i64> = (0..N).map(|_| rng.random_range(-1000..1000)).collect();
+        let mut uf = PotentialUnionFind::<i64>::new(N);
+        let mut labels: WeightedQuickUnion = UnionFind::new(N);
+        for _ in 0..300 {
+            let p = rng.random_range(0..N);
+            let q = rng.random_range(0..N);
+            let d = values[p] - values[q];
+            let connected = labels.connected(p, q);
+            assert_eq!(uf.diff(p, q), connected.then_some(d));
+            if connected && p != q {
+                assert_eq!(
+                    uf.union(p, q, d + 1),
+                    Err(Contradiction {
+                        expected: d + 1,
+                        actual: d
+                    })
+                );
+            }
+            assert_eq!(uf.union(p, q, d), Ok(!connected));
+            labels.union(p, q);
+            assert_eq!(uf.count(), labels.count());
+            assert_eq!(uf.component_size(p), labels.component_size(p));
+        }
+        assert_eq!(uf.make_set(), N);
+        assert_eq!(uf.union(N, 0, 5), Ok(true));
+        assert_eq!(uf.diff(N, 0), Some(5));
+    }
+
+    #[test]
+    fn test_parity() {
+        // Even cycle 0 - 1 - 2 - 3 - 0 is bipartite
+        let mut uf = PotentialUnionFind::<bool>::new(5);
+        for (p, q) in [(0, 1), (1, 2), (2, 3), (3, 0)] {
+            assert!(uf.union(p, q, true).is_ok());
+        }
+        assert_eq!(uf.diff(0, 2), Some(false));
+        assert_eq!(uf.diff(1, 0), Some(true));
+        assert_eq!(uf.diff(0, 4), None);
+
+        // Closing an odd cycle is a contradiction
+        let error = uf.union(0, 2, true).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "expected a difference of true, but it is already false"
+        );
+        assert_eq!(uf.count(), 2);
+    }
+
+    #[test]
+    fn test_keyed() {
+        let mut uf = KeyedUnionFind::<&str>::new();
+        assert!(uf.is_empty());
+        assert!(uf.union("a", "b"));
+        assert!(uf.union("c", "d"));
+        assert!(!uf.union("b", "a"));
+        assert_eq!(uf.add("e"), 4);
+        assert_eq!(uf.add("c"), 2);
+        assert_eq!((uf.len(), uf.count()), (5, 3));
+        assert!(uf.connected(&"a", &"b"));
+        assert!(!uf.connected(&"a", &"c"));
+        assert!(uf.connected(&"x", &"x") && !uf.connected(&"x", &"a"));
+        assert_eq!(uf.find(&"x"), None);
+        assert_eq!(uf.component_size(&"d"), Some(2));
+
+        assert!(uf.union("d", "a"));
+        let root = *uf.find(&"a").unwrap();
+        assert_eq!(uf.find(&"c"), Some(&root));
+        assert_eq!(uf.id(&"e"), Some(4));
+        assert_eq!(*uf.key(4), "e");
+        let components: Vec<_> = uf.components().collect();
+        assert_eq!(components, [vec![&"a", &"b", &"c", &"d"], vec![&"e"]]);
+    }
+
+    #[test]
+    fn test_access_counts() {
+        let mut uf = QuickFind::<usize, true>::new(10);
+        // Two finds, a pass over id rewriting one entry, and the size update
+        assert!(uf.union(1, 2));
+        assert_eq!(uf.accesses(), 2 + 10 + 1 + 3);
+        assert!(uf.connected(1, 2));
+        assert_eq!(uf.accesses(), 18);
+
+        // A chain 0 <- 1 <- ... <- 9, find(0) follows it to the root
+        let mut uf = QuickUnion::<u32, true>::new(10);
+        (1..10).for_each(|p| {
+            uf.union(p - 1, p);
+        });
+        let before = uf.accesses();
+        assert_eq!(uf.find(0), 9);
+        assert_eq!(uf.accesses() - before, 10);
+
+        // Halving shortens the same chain to half its length
+        let mut uf = WeightedQuickUnionWithPathHalving::<usize, true>::new(10);
+        (1..10).for_each(|p| {
+            uf.set_id(p - 1, p);
+        });
+        assert_eq!(uf.find(0), 9);
+        let before = uf.accesses();
+        assert_eq!(uf.find(0), 9);
+        assert!(uf.accesses() - before <= 3 * 5 + 1);
+
+        let mut uf: WeightedQuickUnion = UnionFind::new(10);
+        uf.union(0, 1);
+        assert_eq!(uf.accesses(), 0);
     }
 }